@@ -0,0 +1,94 @@
+//! A stand-in OAuth2 provider for trying the OAuth login locally.
+//!
+//! ```sh
+//! MOCK_OAUTH_USER=alice cargo run --example mock_oauth_provider
+//! ```
+//!
+//! `/authorize` approves every request as `MOCK_OAUTH_USER` and redirects straight
+//! back with a code; `/token` and `/userinfo` turn that code into an OIDC-style
+//! identity. Point a provider in `oauth.json` at it:
+//!
+//! ```json
+//! {
+//!   "providers": [
+//!     {
+//!       "name": "mock",
+//!       "client_id": "mock",
+//!       "client_secret": "mock",
+//!       "authorize_url": "http://localhost:9090/authorize",
+//!       "token_url": "http://localhost:9090/token",
+//!       "userinfo_url": "http://localhost:9090/userinfo",
+//!       "redirect_uri": "http://localhost:3001/oauth/mock"
+//!     }
+//!   ]
+//! }
+//! ```
+
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    redirect_uri: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    code: String,
+}
+
+async fn authorize(Query(query): Query<AuthorizeQuery>) -> Redirect {
+    let user = env::var("MOCK_OAUTH_USER").unwrap_or_else(|_| "mockuser".to_string());
+    let separator = if query.redirect_uri.contains('?') { '&' } else { '?' };
+
+    Redirect::to(&format!(
+        "{}{}code=mock-{}&state={}",
+        query.redirect_uri, separator, user, query.state
+    ))
+}
+
+/// The code doubles as the access token.
+async fn token(Form(form): Form<TokenForm>) -> impl IntoResponse {
+    Json(json!({
+        "access_token": form.code,
+        "token_type": "Bearer",
+        "expires_in": 3600
+    }))
+}
+
+async fn userinfo(headers: HeaderMap) -> impl IntoResponse {
+    let user = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer mock-"));
+
+    match user {
+        Some(user) => (StatusCode::OK, Json(json!({
+            "sub": format!("mock-{}", user),
+            "preferred_username": user,
+            "name": user
+        }))),
+        None => (StatusCode::UNAUTHORIZED, Json(json!({"error": "invalid_token"}))),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let app = Router::new()
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/userinfo", get(userinfo));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:9090").await.unwrap();
+    println!("Mock OAuth provider listening on http://127.0.0.1:9090");
+    axum::serve(listener, app).await.unwrap();
+}
@@ -0,0 +1,39 @@
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    team_id INTEGER,
+    name TEXT NOT NULL,
+    username TEXT NOT NULL UNIQUE,
+    ign TEXT NOT NULL,
+    password TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    selections TEXT,
+    team_size INTEGER NOT NULL,
+    team_money INTEGER NOT NULL,
+    is_picking BOOLEAN NOT NULL,
+    created_by TEXT
+);
+
+CREATE TABLE IF NOT EXISTS players (
+    name TEXT NOT NULL,
+    peak_rank TEXT NOT NULL,
+    current_rank TEXT NOT NULL,
+    teammate_preferences TEXT,
+    roles TEXT,
+    ign TEXT NOT NULL PRIMARY KEY,
+    current_rank_order INTEGER NOT NULL,
+    peak_rank_order INTEGER NOT NULL,
+    drafted BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS draft_state (
+    id INTEGER PRIMARY KEY,
+    phase TEXT NOT NULL,
+    teams TEXT NOT NULL,
+    current_turn INTEGER NOT NULL,
+    drafted_players TEXT NOT NULL,
+    direction INTEGER NOT NULL DEFAULT 1
+);
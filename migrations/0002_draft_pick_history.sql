@@ -0,0 +1 @@
+ALTER TABLE draft_state ADD COLUMN pick_history TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    deleted BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS chat_mutes (
+    username TEXT PRIMARY KEY,
+    muted_until TEXT,
+    muted_by TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'player';
+
+-- Everyone who already owns a team keeps the ability to draft for it.
+UPDATE users SET role = 'captain' WHERE username IN (SELECT created_by FROM teams WHERE created_by IS NOT NULL);
+
+-- Admin rights used to come from the username alone; carry that account over.
+UPDATE users SET role = 'admin' WHERE username = 'admin';
@@ -0,0 +1,6 @@
+-- Failed login tracking, keyed by `account:<username>` or `ip:<address>`.
+CREATE TABLE IF NOT EXISTS login_throttle (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    locked_until INTEGER
+);
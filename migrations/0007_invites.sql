@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS invites (
+    code TEXT PRIMARY KEY,
+    player_ign TEXT,
+    role TEXT NOT NULL DEFAULT 'player',
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    used_by TEXT,
+    used_at TEXT
+);
+
+ALTER TABLE users ADD COLUMN ign_verified BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,19 @@
+ALTER TABLE users ADD COLUMN player_ign TEXT REFERENCES players(ign) ON DELETE SET NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_player_ign ON users(player_ign) WHERE player_ign IS NOT NULL;
+
+-- Accounts registered through a signup invite already have a verified IGN.
+UPDATE users SET player_ign = ign
+WHERE ign_verified = 1
+  AND ign IN (SELECT ign FROM players)
+  AND ign NOT IN (SELECT player_ign FROM users WHERE player_ign IS NOT NULL);
+
+CREATE TABLE IF NOT EXISTS player_claims (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL,
+    player_ign TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at TEXT NOT NULL,
+    reviewed_by TEXT,
+    reviewed_at TEXT
+);
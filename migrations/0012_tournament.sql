@@ -0,0 +1,10 @@
+-- Settings of the tournament being run. There is only ever one row.
+CREATE TABLE IF NOT EXISTS tournament (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    player_source TEXT NOT NULL,
+    source_upload TEXT
+);
+
+-- Signups used to come from this sheet, hard-coded.
+INSERT OR IGNORE INTO tournament (id, player_source)
+VALUES (1, '{"kind":"google_sheets","credentials_file":"credentials.json","spreadsheet_id":"1_57KqAux4swU4QAdQXeEd--eDDSFZzF_FXVosagzAQU","range":"Form Responses 1"}');
@@ -0,0 +1,7 @@
+-- Signups read by a dry-run import, kept so committing applies exactly what was previewed
+CREATE TABLE IF NOT EXISTS player_import_previews (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_by TEXT NOT NULL,
+    signups TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Which signup column feeds which player field, as a JSON object keyed by player source kind.
+-- Sources without an entry use the columns of the original Google Form.
+ALTER TABLE tournament ADD COLUMN column_mappings TEXT NOT NULL DEFAULT '{}';
+
+-- Extra signup columns kept as a JSON object of header to answer
+ALTER TABLE players ADD COLUMN attributes TEXT NOT NULL DEFAULT '{}';
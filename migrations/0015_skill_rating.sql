@@ -0,0 +1,16 @@
+-- A rating blending current and peak rank, in divisions above unranked (Iron 1 is 1).
+ALTER TABLE players ADD COLUMN skill_rating REAL NOT NULL DEFAULT 0;
+
+-- Existing players only have base tiers, so assume the middle division until the next import
+UPDATE players SET skill_rating = (
+    SELECT CASE
+        WHEN current = 0 THEN peak
+        WHEN peak = 0 THEN current
+        ELSE 0.7 * current + 0.3 * MAX(peak, current)
+    END
+    FROM (
+        SELECT
+            CASE WHEN current_rank_order = 9 THEN 25 WHEN current_rank_order > 0 THEN (current_rank_order - 1) * 3 + 2 ELSE 0 END AS current,
+            CASE WHEN peak_rank_order = 9 THEN 25 WHEN peak_rank_order > 0 THEN (peak_rank_order - 1) * 3 + 2 ELSE 0 END AS peak
+    )
+);
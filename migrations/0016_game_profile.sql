@@ -0,0 +1,2 @@
+-- The game's rank ladder and roles as JSON. Tournaments without one play Valorant.
+ALTER TABLE tournament ADD COLUMN game_profile TEXT;
@@ -0,0 +1,3 @@
+-- sessions_valid_after is now unix milliseconds, so a session ended and a new
+-- one started within the same second can still be told apart.
+UPDATE users SET sessions_valid_after = sessions_valid_after * 1000 WHERE sessions_valid_after IS NOT NULL;
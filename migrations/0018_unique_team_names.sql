@@ -0,0 +1,5 @@
+-- Team names are unique regardless of case. Older duplicates get their id appended first.
+UPDATE teams SET name = name || ' (' || id || ')'
+WHERE id NOT IN (SELECT MIN(id) FROM teams GROUP BY name COLLATE NOCASE);
+
+CREATE UNIQUE INDEX IF NOT EXISTS teams_name_unique ON teams(name COLLATE NOCASE);
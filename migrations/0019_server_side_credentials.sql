@@ -0,0 +1,3 @@
+-- The Google credentials file is now server configuration (GOOGLE_CREDENTIALS_FILE),
+-- not a tournament setting.
+UPDATE tournament SET player_source = json_remove(player_source, '$.credentials_file');
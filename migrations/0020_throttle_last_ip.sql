@@ -0,0 +1,3 @@
+-- The address an account's last failure came from, so unlocking the account
+-- can also unlock that address.
+ALTER TABLE login_throttle ADD COLUMN last_ip TEXT;
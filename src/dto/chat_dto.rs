@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A draft room chat message. System announcements have no `username`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ChatMessage {
+    pub id: i64,
+    pub username: Option<String>,
+    pub body: String,
+    pub created_at: String,
+    pub deleted: bool
+}
+
+#[derive(Serialize)]
+pub struct ChatMessageUpdate {
+    pub r#type: String,
+    pub message: ChatMessage
+}
+
+#[derive(Serialize)]
+pub struct ChatDeleted {
+    pub r#type: String,
+    pub id: i64
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatHistoryQuery {
+    pub before: Option<i64>,
+    pub limit: Option<i64>
+}
@@ -1,7 +1,27 @@
 use serde::{Deserialize, Serialize};
 
+use crate::services::rbac::Role;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
-    pub exp: usize, 
-}
\ No newline at end of file
+    pub exp: usize,
+    #[serde(default)]
+    pub iat: usize,
+    /* `iat` in milliseconds, compared against `sessions_valid_after` */
+    #[serde(default)]
+    pub iat_ms: i64,
+    #[serde(default)]
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+impl Claims {
+    /** When the token was issued, in unix milliseconds. Tokens minted before `iat_ms` existed only carry `iat`. */
+    pub fn issued_at_ms(&self) -> i64 {
+        if self.iat_ms > 0 { self.iat_ms } else { self.iat as i64 * 1000 }
+    }
+}
@@ -4,6 +4,9 @@ use sqlx::types::Json;
 use crate::dto::{player_dto::Player, team_dto::Team};
 use tokio::sync::RwLock;
 use std::sync::Arc;
+use validator::Validate;
+
+use crate::services::validation::validate_not_blank;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct DraftState {
@@ -11,7 +14,17 @@ pub struct DraftState {
     pub teams: Json<Vec<Team>>,
     pub current_turn: i64,         
     pub drafted_players: Json<Vec<Player>>,
-    pub direction: i64
+    pub direction: i64,
+    pub pick_history: Json<Vec<PickRecord>>
+}
+
+/// One entry per pick made during the draft, holding the turn and direction
+/// that were active before the pick so it can be undone.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PickRecord {
+    pub turn: i64,
+    pub direction: i64,
+    pub ign: String
 }
 
 impl Default for DraftState {
@@ -21,11 +34,23 @@ impl Default for DraftState {
             teams: Json(vec![]),
             current_turn: 0,
             drafted_players: Json(vec![]),
-            direction: 1
+            direction: 1,
+            pick_history: Json(vec![])
         }
     }
 }
 
+/// Body of `POST /draft/pick`. Clients that send the whole player still work;
+/// everything but the IGN is ignored.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PickPlayer {
+    #[validate(
+        length(min = 1, max = 64, message = "must be 1 to 64 characters"),
+        custom(function = "validate_not_blank")
+    )]
+    pub ign: String
+}
+
 #[derive(Serialize)]
 pub struct UpdateDraft {
     pub r#type: String,
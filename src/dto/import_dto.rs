@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dto::player_dto::{Player, PlayerCard};
+
+/**
+ * Something wrong with one row of the signup table. Rows are numbered as in
+ * the spreadsheet, so the header is row 1. `skipped` rows were left out of the
+ * import; the rest were imported with the problem cell left blank.
+ */
+#[derive(Debug, Serialize, Clone)]
+pub struct RowProblem {
+    pub row: usize,
+    pub column: Option<String>,
+    pub problem: String,
+    pub skipped: bool
+}
+
+/// The signups that could be read from a signup table, and what was wrong with the rest.
+#[derive(Debug, Default)]
+pub struct FormattedSignups {
+    pub players: Vec<PlayerCard>,
+    pub problems: Vec<RowProblem>
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerChange {
+    pub ign: String,
+    pub changes: Vec<FieldChange>
+}
+
+/// What committing a previewed import would do to the player pool.
+#[derive(Debug, Serialize)]
+pub struct ImportDiff {
+    pub preview_id: i64,
+    pub new_players: Vec<PlayerCard>,
+    pub changed: Vec<PlayerChange>,
+    /// In the pool but no longer in the source. Only undrafted ones can be removed.
+    pub missing: Vec<Player>,
+    pub unchanged: usize,
+    /// Rows of the signup table that were skipped or only partly read.
+    pub problems: Vec<RowProblem>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportCommit {
+    pub preview_id: i64,
+    /// IGNs of new or changed players to write. Omit to write all of them.
+    pub apply: Option<Vec<String>>,
+    /// IGNs of missing players to remove from the pool.
+    #[serde(default)]
+    pub remove: Vec<String>
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::services::rbac::Role;
+
+/// A single-use registration code. Invites with a `player_ign` bind the new
+/// account to that signup and mark its IGN as verified.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Invite {
+    pub code: String,
+    pub player_ign: Option<String>,
+    pub role: Role,
+    pub created_by: String,
+    pub created_at: String,
+    pub used_by: Option<String>,
+    pub used_at: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvite {
+    pub player_ign: Option<String>,
+    pub role: Option<Role>
+}
@@ -2,4 +2,12 @@ pub mod team_dto;
 pub mod player_dto;
 pub mod user_dto;
 pub mod claims_dto;
-pub mod draft_dto;
\ No newline at end of file
+pub mod draft_dto;
+pub mod ws_dto;
+pub mod chat_dto;
+pub mod invite_dto;
+
+pub mod oauth_dto;
+pub mod audit_dto;
+pub mod tournament_dto;
+pub mod import_dto;
\ No newline at end of file
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthAuthorizeQuery {
+    /// Needed the first time someone signs in with a provider, to create their account.
+    pub invite_code: Option<String>
+}
+
+/// The `code` and `state` the provider redirected back to the frontend with.
+#[derive(Debug, Deserialize, Validate)]
+pub struct OAuthCallback {
+    #[validate(length(min = 1, max = 2048, message = "must be 1 to 2048 characters"))]
+    pub code: String,
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters"))]
+    pub state: String
+}
@@ -1,28 +1,23 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow};
+use sqlx::{types::Json, FromRow};
+use std::collections::BTreeMap;
+use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::services::validation::validate_not_blank;
+
+/// One signup as read from the columns of the signup table, before ranks are parsed.
+#[derive(Debug, Clone, Default)]
 pub struct RawPlayer {
-    #[serde(rename = "Your Name")]
     pub name: String,
-
-    #[serde(rename = "Your highest rank achieved on your main")]
     pub peak_rank: String,
-
-    #[serde(rename = "Your current rank on your main")]
     pub current_rank: String,
-
-    #[serde(rename = "Do you have any teammate preferences? While we can't guarantee you'll be placed with them, listing preferences will increase your chances.")]
     pub teammate_preferences: String,
-
-    #[serde(rename = "Role preferences")]
     pub roles: String,
-
-    #[serde(rename = "In game name (including #)")]
     pub ign: String,
+    pub attributes: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PlayerCard {
     pub name: String,
     pub peak_rank: String,
@@ -32,6 +27,12 @@ pub struct PlayerCard {
     pub ign: String,
     pub current_rank_order: u8,
     pub peak_rank_order: u8,
+    /// Current and peak rank blended into one number, higher is better.
+    #[serde(default)]
+    pub skill_rating: f64,
+    /// Answers from the extra columns in the column mapping, by header.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -44,11 +45,97 @@ pub struct Player {
     pub ign: String,
     pub current_rank_order: i64,
     pub peak_rank_order: i64,
-    pub drafted: bool
+    pub drafted: bool,
+    #[serde(default)]
+    pub attributes: Json<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub skill_rating: f64
 }
 
 #[derive(Serialize)]
 pub struct PlayerUpdate {
     pub r#type: String,
     pub players: Vec<Player>
-}
\ No newline at end of file
+}
+
+/// A user's request to be linked to a signup, reviewed by an admin.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct PlayerClaim {
+    pub id: i64,
+    pub username: String,
+    pub player_ign: String,
+    pub status: String,
+    pub created_at: String,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<String>
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ClaimPlayer {
+    #[validate(
+        length(min = 1, max = 64, message = "must be 1 to 64 characters"),
+        custom(function = "validate_not_blank")
+    )]
+    pub ign: String
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlayerClaimQuery {
+    pub status: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewClaim {
+    pub approve: bool
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerSort {
+    #[default]
+    SkillRating,
+    CurrentRank,
+    PeakRank,
+    Name,
+    Ign
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc
+}
+
+/**
+ * Filters for the player list, used by `GET /players` and the websocket
+ * `players` request. Ranks are positions in the game profile's ladder.
+ */
+#[derive(Debug, Deserialize, Default)]
+pub struct PlayerQuery {
+    pub drafted: Option<bool>,
+    /// Matched against name and IGN.
+    pub search: Option<String>,
+    /// One of the player's role preferences, e.g. `Duelist`.
+    pub role: Option<String>,
+    pub min_rank: Option<i64>,
+    pub max_rank: Option<i64>,
+    pub min_rating: Option<f64>,
+    pub max_rating: Option<f64>,
+    #[serde(default)]
+    pub sort: PlayerSort,
+    /// Defaults to best first for ranks and A to Z for names.
+    pub order: Option<SortOrder>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>
+}
+
+/// Reply to a websocket `players` request, sent only to the client that asked.
+#[derive(Serialize)]
+pub struct PlayerSnapshot {
+    pub r#type: String,
+    pub id: Option<String>,
+    /// Players matching the filters, before `limit` and `offset`.
+    pub total: i64,
+    pub players: Vec<Player>
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use validator::Validate;
+
+use crate::services::validation::validate_not_blank;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Team {
@@ -18,8 +21,13 @@ pub struct TeamsUpdate {
     pub teams: Vec<Team>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateTeam {
+    #[validate(
+        length(min = 1, max = 32, message = "must be 1 to 32 characters"),
+        custom(function = "validate_not_blank")
+    )]
     pub name: String,
+    #[validate(length(max = 10, message = "must have at most 10 entries"))]
     pub selections: Vec<String>
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::services::validation::validate_not_blank;
+
+/// Where the tournament's signups are imported from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PlayerSourceConfig {
+    /// A Google Sheet read with the server's service account, see `GOOGLE_CREDENTIALS_FILE`.
+    GoogleSheets {
+        spreadsheet_id: String,
+        range: String
+    },
+    /// The last uploaded CSV file, with a header row.
+    Csv,
+    /// The last uploaded JSON file: an array of objects, or an array of rows
+    /// whose first row is the header.
+    Json
+}
+
+impl PlayerSourceConfig {
+    /// The `kind` tag, which column mappings are keyed by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PlayerSourceConfig::GoogleSheets { .. } => "google_sheets",
+            PlayerSourceConfig::Csv => "csv",
+            PlayerSourceConfig::Json => "json",
+        }
+    }
+}
+
+/// Every `kind` of player source.
+pub const PLAYER_SOURCE_KINDS: [&str; 3] = ["google_sheets", "csv", "json"];
+
+/// A column of the signup table, by position (0 is the first column) or header text.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Header(String)
+}
+
+/**
+ * Which column of the signup table each player field is read from, e.g.
+ * `{"name": "Your Name", "ign": 2, "current_rank": "Current rank", "attributes": ["Timezone"]}`.
+ * `attributes` lists extra columns to keep on the player under their header.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct ColumnMapping {
+    #[validate(required(message = "must be mapped to a column"))]
+    pub name: Option<ColumnRef>,
+    #[validate(required(message = "must be mapped to a column"))]
+    pub ign: Option<ColumnRef>,
+    #[validate(required(message = "must be mapped to a column"))]
+    pub current_rank: Option<ColumnRef>,
+    pub peak_rank: Option<ColumnRef>,
+    pub teammate_preferences: Option<ColumnRef>,
+    pub roles: Option<ColumnRef>,
+    #[serde(default)]
+    #[validate(length(max = 32, message = "must have at most 32 entries"))]
+    pub attributes: Vec<ColumnRef>
+}
+
+/// The questions of the Google Form signups were first collected with.
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        let header = |text: &str| Some(ColumnRef::Header(text.to_string()));
+
+        Self {
+            name: header("Your Name"),
+            ign: header("In game name (including #)"),
+            current_rank: header("Your current rank on your main"),
+            peak_rank: header("Your highest rank achieved on your main"),
+            teammate_preferences: header("Do you have any teammate preferences? While we can't guarantee you'll be placed with them, listing preferences will increase your chances."),
+            roles: header("Role preferences"),
+            attributes: vec![]
+        }
+    }
+}
+
+/// A tier of a game's rank ladder, e.g. Gold, with the spellings players use for it.
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct RankTier {
+    #[validate(
+        length(min = 1, max = 32, message = "must be 1 to 32 characters"),
+        custom(function = "validate_not_blank")
+    )]
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Divisions in this tier, if not the profile's usual number. Top tiers often have none.
+    #[validate(range(max = 10, message = "must be at most 10"))]
+    pub divisions: Option<u8>
+}
+
+/// A role players sign up for, e.g. Jungle, with the spellings players use for it.
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct GameRole {
+    #[validate(
+        length(min = 1, max = 32, message = "must be 1 to 32 characters"),
+        custom(function = "validate_not_blank")
+    )]
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>
+}
+
+/**
+ * How ranks and roles work in the tournament's game. `ranks` go from lowest
+ * to highest; each has `divisions` divisions unless it says otherwise.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct GameProfile {
+    #[validate(
+        length(min = 1, max = 64, message = "must be 1 to 64 characters"),
+        custom(function = "validate_not_blank")
+    )]
+    pub game: String,
+    #[validate(length(min = 1, max = 50, message = "must have 1 to 50 entries"), nested)]
+    pub ranks: Vec<RankTier>,
+    #[serde(default)]
+    #[validate(range(max = 10, message = "must be at most 10"))]
+    pub divisions: u8,
+    /// Whether division 1 is the best of its tier, as in League of Legends and Overwatch.
+    #[serde(default)]
+    pub division_one_is_highest: bool,
+    /// Roles signups are matched against. Left empty, roles are kept as written.
+    #[serde(default)]
+    #[validate(length(max = 20, message = "must have at most 20 entries"), nested)]
+    pub roles: Vec<GameRole>
+}
@@ -1,26 +1,121 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+use crate::services::rbac::Role;
+use crate::services::validation::{validate_ign, validate_not_blank, validate_username};
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginUser {
+    #[validate(length(min = 1, max = 64, message = "must be 1 to 64 characters"))]
     pub username: String,
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters"))]
     pub password: String
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateUser {
+    #[validate(
+        length(min = 1, max = 64, message = "must be 1 to 64 characters"),
+        custom(function = "validate_not_blank")
+    )]
     pub name: String,
+    #[validate(
+        length(min = 3, max = 32, message = "must be 3 to 32 characters"),
+        custom(function = "validate_username")
+    )]
     pub username: String,
-    pub ign: String,
-    pub password: String
+    /// Only used when the invite is not tied to a signup; otherwise the IGN comes from the invite.
+    #[validate(custom(function = "validate_ign"))]
+    pub ign: Option<String>,
+    #[validate(length(min = 8, max = 128, message = "must be 8 to 128 characters"))]
+    pub password: String,
+    #[validate(length(min = 1, max = 32, message = "must be 1 to 32 characters"))]
+    pub invite_code: String
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: i64,
-    pub team_id: i64,
+    pub team_id: Option<i64>,
     pub name: String,
     pub username: String,
     pub ign: String,
-    pub password: String
-}
\ No newline at end of file
+    pub password: String,
+    pub role: Role,
+    pub ign_verified: bool,
+    /// The signup this account plays as, once linked by invite or an approved claim.
+    pub player_ign: Option<String>,
+    pub disabled: bool
+}
+
+/// A user as admins see them, without the password and with the team they own.
+#[derive(Debug, Serialize, FromRow)]
+pub struct UserSummary {
+    pub id: i64,
+    pub name: String,
+    pub username: String,
+    pub ign: String,
+    pub role: Role,
+    pub ign_verified: bool,
+    pub player_ign: Option<String>,
+    pub team_id: Option<i64>,
+    pub disabled: bool,
+    pub owned_team_id: Option<i64>,
+    pub owned_team_name: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchQuery {
+    /// Matched against username, name and IGN.
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveUserQuery {
+    /// Who takes over the removed user's team. Required while the team is in a draft.
+    pub transfer_to: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetDisabled {
+    pub disabled: bool
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPassword {
+    #[validate(length(min = 1, max = 64, message = "must be 1 to 64 characters"))]
+    pub username: String,
+    #[validate(length(min = 1, max = 32, message = "must be 1 to 32 characters"))]
+    pub code: String,
+    #[validate(length(min = 8, max = 128, message = "must be 8 to 128 characters"))]
+    pub new_password: String
+}
+
+/// The role itself is checked when it is deserialized; there are no other rules.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRole {
+    pub role: Role
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshToken {
+    pub refresh_token: String
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutUser {
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub all: bool
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(length(min = 8, max = 128, message = "must be 8 to 128 characters"))]
+    pub new_password: String
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dto::player_dto::PlayerQuery;
+use crate::services::rbac::Permission;
+
+/// A command sent by a client over `/ws`, e.g.
+/// `{"type": "pick", "id": "1", "ign": "Player#NA1"}`.
+#[derive(Debug, Deserialize)]
+pub struct ClientMessage {
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub command: ClientCommand
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientCommand {
+    Pick { ign: String },
+    Pause,
+    Resume,
+    Undo,
+    Send { channel: String, message: String },
+    Chat { message: String },
+    ChatDelete { message_id: i64 },
+    ChatMute { username: String, minutes: Option<i64> },
+    ChatUnmute { username: String }
+}
+
+impl ClientCommand {
+    pub const TYPES: [&'static str; 9] = [
+        "pick", "pause", "resume", "undo", "send",
+        "chat", "chat_delete", "chat_mute", "chat_unmute",
+    ];
+
+    pub fn required_permission(&self) -> Permission {
+        match self {
+            ClientCommand::Pick { .. } => Permission::DraftPick,
+            ClientCommand::Pause | ClientCommand::Resume | ClientCommand::Undo => Permission::ManageDraft,
+            ClientCommand::Send { .. } | ClientCommand::Chat { .. } => Permission::Chat,
+            ClientCommand::ChatDelete { .. }
+            | ClientCommand::ChatMute { .. }
+            | ClientCommand::ChatUnmute { .. } => Permission::ModerateChat,
+        }
+    }
+}
+/// What a broadcast message is, set by the code that built it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    DraftUpdate,
+    TeamsUpdate,
+    PlayerUpdate,
+    ChatMessage,
+    ChatDeleted
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::DraftUpdate => "draft_update",
+            EventKind::TeamsUpdate => "teams_update",
+            EventKind::PlayerUpdate => "player_update",
+            EventKind::ChatMessage => "chat_message",
+            EventKind::ChatDeleted => "chat_deleted",
+        }
+    }
+
+    /// Whether the event may be served to anonymous SSE clients and replayed.
+    pub fn is_public(&self) -> bool {
+        matches!(self, EventKind::DraftUpdate | EventKind::TeamsUpdate | EventKind::PlayerUpdate)
+    }
+}
+
+/**
+ * A message the server sends to every websocket client. Only server code
+ * builds these; `kind` is never read back from the text of `data`.
+ */
+#[derive(Debug, Clone)]
+pub struct ServerEvent {
+    pub kind: EventKind,
+    pub data: String
+}
+
+/// A request for the player list with the filters of `GET /players`, e.g.
+/// `{"type": "players", "id": "2", "drafted": false, "role": "Duelist"}`.
+/// Anyone connected may send it, including spectators.
+#[derive(Debug, Deserialize)]
+pub struct PlayerSnapshotRequest {
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub query: PlayerQuery
+}
+
+/// Reply sent only to the client that issued the command.
+#[derive(Serialize)]
+pub struct CommandReply {
+    pub r#type: String,
+    pub id: Option<String>,
+    pub message: String
+}
+
+/// A message delivered on a private channel (`user:<name>`, `team:<id>` or `admin`).
+#[derive(Serialize)]
+pub struct ChannelMessage {
+    pub r#type: String,
+    pub channel: String,
+    pub from: String,
+    pub message: String
+}
+
+/// Sent on the captain's `user:` channel when their team comes up to pick.
+#[derive(Serialize)]
+pub struct OnTheClock {
+    pub r#type: String,
+    pub channel: String,
+    pub team_id: i64,
+    pub team_name: String
+}
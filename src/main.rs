@@ -1,23 +1,38 @@
 use axum::{
-    extract::{Extension}, http::{HeaderValue, Method}, routing::{get, post, delete}, Router
+    extract::{Extension}, http::{header, HeaderName, HeaderValue, Method}, routing::{get, post, put, delete}, Router
 };
 use tower_http::cors::{CorsLayer};
-use sqlx::{sqlite::SqlitePoolOptions, types::Json};
+use sqlx::sqlite::SqlitePoolOptions;
 use tracing::{info, error};
 use tokio::sync::{broadcast};
-use tokio::sync::RwLock;
 use std::sync::Arc;
+use std::net::SocketAddr;
 
 mod dto;
 mod services;
 mod routes;
 
-use dto::draft_dto::{DraftState, SharedDraftState};
+use dto::ws_dto::ServerEvent;
 
 use routes::teams::{get_teams, create_teams, delete_teams};
-use routes::users::{create_user, login_user, remove_user};
+use routes::users::{
+    create_user, login_user, remove_user, update_user_role, refresh_token, logout_user, change_password, unlock_user,
+    get_users, delete_user, set_user_disabled, issue_password_reset, reset_password,
+};
 use routes::draft::{start_draft, get_state_internal, draft_pick, get_state, stop_draft};
-use routes::players::get_players;
+use routes::players::{get_players, import_players, preview_import, commit_import};
+use services::ws_connections::{ConnectionTracker, WebSocketConfig};
+use services::event_stream::{EventLog, sse_handler};
+use services::channels::ChannelRegistry;
+use services::chat::ChatLimiter;
+use services::jwt_keys::JwtKeys;
+use services::oauth::OAuthProviders;
+use routes::chat::get_chat_history;
+use routes::invites::{get_invites, create_invites, issue_signup_invites, delete_invite};
+use routes::player_claims::{claim_player, get_player_claims, review_player_claim};
+use routes::oauth::{oauth_authorize, oauth_link, oauth_callback};
+use routes::audit::get_audit_log;
+use routes::tournament::{get_player_source, set_player_source, upload_player_source, get_column_mapping, set_column_mapping, get_game_profile, get_game_profile_presets, set_game_profile};
 
 
 #[tokio::main]
@@ -37,7 +52,11 @@ async fn main() {
                 .collect::<Vec<_>>(),
         )
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers(tower_http::cors::Any);
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        // Paged player searches report their total here
+        .expose_headers([HeaderName::from_static("x-total-count")])
+        // The OAuth state cookie has to travel with the frontend's requests
+        .allow_credentials(true);
 
     let db_url = "sqlite://./data/sunny.db";
     let pool = SqlitePoolOptions::new()
@@ -48,29 +67,91 @@ async fn main() {
 
     info!("Connected to sqlite database.");
 
-    let (tx, _) = broadcast::channel::<String>(32);
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .expect("Could not run database migrations");
+
+    if let Err(e) = services::invites::ensure_bootstrap_invite(&pool).await {
+        error!("Could not check for an admin account: {:?}", e);
+    }
+
+    let (tx, _) = broadcast::channel::<ServerEvent>(32);
+    let event_log = EventLog::spawn(&tx);
 
     let draft_state = get_state_internal(&pool).await;
+    let connections = Arc::new(ConnectionTracker::new(WebSocketConfig::from_env()));
+    let channels = Arc::new(ChannelRegistry::default());
+    let chat = Arc::new(ChatLimiter::default());
+    let jwt_keys = match JwtKeys::from_env() {
+        Ok(keys) => Arc::new(keys),
+        Err(e) => {
+            error!("Could not load JWT keys: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let oauth_providers = Arc::new(OAuthProviders::from_env().expect("Could not load OAuth providers"));
     
     let app = Router::new()
         .route("/ws", get(services::websocket::websocket_handler))
+        .route("/ws/metrics", get(services::websocket::get_ws_metrics))
+        .route("/events", get(sse_handler))
         .route("/teams", get(get_teams))
         .route("/teams", post(create_teams))
         .route("/teams/{team_id}", delete(delete_teams))
         .route("/players", get(get_players))
+        .route("/players/import", post(import_players))
+        .route("/players/import/preview", post(preview_import))
+        .route("/players/import/commit", post(commit_import))
+        .route("/players/claim", post(claim_player))
+        .route("/players/claims", get(get_player_claims))
+        .route("/players/claims/{claim_id}", post(review_player_claim))
         .route("/login", post(login_user))
+        .route("/logout", post(logout_user))
+        .route("/token/refresh", post(refresh_token))
+        .route("/oauth/{provider}/authorize", get(oauth_authorize))
+        .route("/oauth/{provider}/link", get(oauth_link))
+        .route("/oauth/{provider}/callback", post(oauth_callback))
+        .route("/users", get(get_users))
         .route("/users", post(create_user))
         .route("/users", delete(remove_user))
+        .route("/users/password", put(change_password))
+        .route("/users/password/reset", post(reset_password))
+        .route("/users/{username}", delete(delete_user))
+        .route("/users/{username}/disabled", put(set_user_disabled))
+        .route("/users/{username}/password_reset", post(issue_password_reset))
+        .route("/users/{username}/role", put(update_user_role))
+        .route("/users/{username}/unlock", post(unlock_user))
+        .route("/invites", get(get_invites))
+        .route("/invites", post(create_invites))
+        .route("/invites/players", post(issue_signup_invites))
+        .route("/invites/{code}", delete(delete_invite))
         .route("/start_draft", post(start_draft))
         .route("/draft/pick", post(draft_pick))
         .route("/stop_draft", post(stop_draft))
         .route("/draft", get(get_state))
+        .route("/chat", get(get_chat_history))
+        .route("/audit", get(get_audit_log))
+        .route("/tournament/player_source", get(get_player_source))
+        .route("/tournament/player_source", put(set_player_source))
+        .route("/tournament/player_source/upload", put(upload_player_source))
+        .route("/tournament/column_mappings/{kind}", get(get_column_mapping))
+        .route("/tournament/column_mappings/{kind}", put(set_column_mapping))
+        .route("/tournament/game_profile", get(get_game_profile))
+        .route("/tournament/game_profile", put(set_game_profile))
+        .route("/tournament/game_profiles", get(get_game_profile_presets))
         .layer(Extension(pool))
         .layer(Extension(tx))
         .layer(Extension(draft_state))
+        .layer(Extension(connections))
+        .layer(Extension(event_log))
+        .layer(Extension(channels))
+        .layer(Extension(chat))
+        .layer(Extension(jwt_keys))
+        .layer(Extension(oauth_providers))
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     info!("Started server.");
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use axum::{
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::SqlitePool;
+use tracing::error;
+
+use crate::dto::audit_dto::{AuditQuery, AuditRecord};
+use crate::services::rbac::{require, Authorized};
+
+/**
+ * GET the audit log, newest first. Filter by `actor`, `action`, `target` and a
+ * `since`/`until` time range; page with `limit` and `offset`.
+ */
+pub async fn get_audit_log(
+    _: Authorized<require::ViewAuditLog>,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<AuditQuery>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let records = sqlx::query_as::<_, AuditRecord>(
+        r#"
+        SELECT * FROM audit_log
+        WHERE (?1 IS NULL OR actor = ?1)
+          AND (?2 IS NULL OR action = ?2)
+          AND (?3 IS NULL OR target = ?3)
+          AND (?4 IS NULL OR created_at >= ?4)
+          AND (?5 IS NULL OR created_at <= ?5)
+        ORDER BY id DESC
+        LIMIT ?6 OFFSET ?7
+        "#
+    )
+    .bind(&query.actor)
+    .bind(&query.action)
+    .bind(&query.target)
+    .bind(&query.since)
+    .bind(&query.until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch audit log: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch audit log")
+    })?;
+
+    Ok((StatusCode::OK, Json(records)))
+}
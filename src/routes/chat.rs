@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::SqlitePool;
+use tracing::error;
+
+use crate::dto::chat_dto::{ChatHistoryQuery, ChatMessage};
+
+/**
+ * GET the chat history, newest first. Pass the smallest `id` seen as `before`
+ * to load the previous page.
+ */
+pub async fn get_chat_history(
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<ChatHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let before = query.before.unwrap_or(i64::MAX);
+
+    let messages = sqlx::query_as::<_, ChatMessage>(
+        r#"
+        SELECT id, username, body, created_at, deleted
+        FROM chat_messages
+        WHERE deleted = 0 AND id < ?
+        ORDER BY id DESC
+        LIMIT ?
+        "#
+    )
+    .bind(before)
+    .bind(limit)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch chat history: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch chat history")
+    })?;
+
+    Ok((StatusCode::OK, Json(messages)))
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Extension, Path, Json},
+    extract::{ConnectInfo, Extension, Json},
     http::StatusCode,
     response::IntoResponse,
 };
@@ -9,21 +9,24 @@ use rand::seq::SliceRandom;
 use rand::rng;
 use tokio::sync::RwLock;
 use std::sync::Arc;
+use std::net::SocketAddr;
 use tokio::sync::broadcast;
 
-use crate::{dto::{draft_dto::{DraftState, SharedDraftState}, player_dto::Player, team_dto::Team}};
-use crate::services::{auth_user::AuthUser, websocket::{send_draft_update, send_player_update}};
+use crate::{dto::{draft_dto::{DraftState, PickPlayer, PickRecord, SharedDraftState}, player_dto::Player, team_dto::Team, ws_dto::ServerEvent}};
+use crate::services::audit::{draft_summary, record_draft_action, AuditEntry};
+use crate::services::validation::Valid;
+use crate::services::{rbac::{require, Authorized}, channels::SharedChannelRegistry, chat::post_system_message, websocket::{notify_on_the_clock, send_draft_update, send_player_update}};
 
 pub async fn start_draft (
     Extension(state): Extension<SharedDraftState>,
-    Extension(tx): Extension<broadcast::Sender<String>>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
     Extension(pool): Extension<SqlitePool>,
-    AuthUser(claims): AuthUser
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Authorized(claims, _): Authorized<require::ManageDraft>
 ) -> impl IntoResponse {
-    info!("Starting tournament.");
-    if claims.sub != "admin" {
-        return (StatusCode::UNAUTHORIZED, format!("You must be an admin to start the tournament."))
-    }
+    info!("{} is starting the tournament.", claims.sub);
+    let before = draft_summary(&*state.read().await);
 
     let mut teams: Vec<Team> = match sqlx::query_as::<_, Team>("SELECT * FROM teams")
         .fetch_all(&pool)
@@ -40,7 +43,7 @@ pub async fn start_draft (
 
     teams.shuffle(&mut rng());
 
-    let mut players: Vec<Player> = match sqlx::query_as::<_, Player>("SELECT * FROM players WHERE drafted = true")
+    let players: Vec<Player> = match sqlx::query_as::<_, Player>("SELECT * FROM players WHERE drafted = true")
         .fetch_all(&pool)
         .await {
             Ok(result) => result,
@@ -59,6 +62,7 @@ pub async fn start_draft (
         guard.phase = "Drafting".into();
         guard.drafted_players = SqlxJson(players);
         guard.teams = SqlxJson(teams);
+        guard.pick_history = SqlxJson(vec![]);
         
         info!("state has been updated with tournament set up.");
 
@@ -82,12 +86,13 @@ pub async fn start_draft (
         // Save draft state
         let result = sqlx::query!(
             r#"
-            INSERT INTO draft_state (id, phase, teams, current_turn, drafted_players)
-            VALUES (1, ?, ?, ?, ?)
+            INSERT INTO draft_state (id, phase, teams, current_turn, drafted_players, pick_history)
+            VALUES (1, ?, ?, ?, ?, '[]')
             ON CONFLICT(id) DO UPDATE SET
                 phase = excluded.phase,
                 teams = excluded.teams,
-                drafted_players = excluded.drafted_players
+                drafted_players = excluded.drafted_players,
+                pick_history = excluded.pick_history
             "#,
             guard.phase,
             teams_json,
@@ -105,24 +110,28 @@ pub async fn start_draft (
 
     info!("Saved draft to db.");
 
+    record_draft_action(&pool, &state, AuditEntry {
+        actor: &claims.sub,
+        action: "start_draft",
+        before: Some(before),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
     send_draft_update(&tx, &state).await;
-    (StatusCode::OK, format!("Started the tournament!"))
+    notify_on_the_clock(&channels, &state).await;
+    (StatusCode::OK, "Started the tournament!".to_string())
 }
 
 pub async fn stop_draft (
     Extension(pool): Extension<SqlitePool>,
     Extension(state): Extension<SharedDraftState>,
-    Extension(tx): Extension<broadcast::Sender<String>>,
-    AuthUser(claims): AuthUser
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Authorized(claims, _): Authorized<require::ManageDraft>
 ) -> impl IntoResponse {
-    info!("Stopping tournament.");
-
-    if claims.sub != "admin" {
-        return (
-            StatusCode::UNAUTHORIZED,
-            "You must be an admin to stop the tournament.".to_string()
-        );
-    }
+    info!("{} is stopping the tournament.", claims.sub);
+    let before = draft_summary(&*state.read().await);
 
     {
         let mut guard = state.write().await;
@@ -154,6 +163,14 @@ pub async fn stop_draft (
         );
     }
 
+    record_draft_action(&pool, &state, AuditEntry {
+        actor: &claims.sub,
+        action: "stop_draft",
+        before: Some(before),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
     send_draft_update(&tx, &state).await;
     send_player_update(&pool, &tx).await;
 
@@ -168,7 +185,7 @@ pub async fn get_state_internal (
 ) -> SharedDraftState {
     let draft_state: SharedDraftState = {
         match sqlx::query_as::<_, DraftState>(
-            r#"SELECT phase, teams, current_turn, drafted_players, direction FROM draft_state WHERE id = 1"#
+            r#"SELECT phase, teams, current_turn, drafted_players, direction, pick_history FROM draft_state WHERE id = 1"#
         )
         .fetch_optional(pool)
         .await
@@ -179,7 +196,8 @@ pub async fn get_state_internal (
                     teams: row.teams,
                     current_turn: row.current_turn,
                     drafted_players: row.drafted_players,
-                    direction: row.direction
+                    direction: row.direction,
+                    pick_history: row.pick_history
                 }))
             }
             Ok(None) => {
@@ -198,29 +216,64 @@ pub async fn get_state_internal (
 
 pub async fn draft_pick(
     Extension(state): Extension<SharedDraftState>,
-    Extension(tx): Extension<broadcast::Sender<String>>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
     Extension(pool): Extension<SqlitePool>,
-    AuthUser(claims): AuthUser,
-    Json(payload): Json<Player>
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Authorized(claims, _): Authorized<require::DraftPick>,
+    Valid(payload): Valid<PickPlayer>
 ) -> impl IntoResponse {
-    info!("Drafting player {}", payload.name);
-    let mut state_guard = state.write().await;
+    let before = draft_summary(&*state.read().await);
+
+    match draft_pick_internal(&state, &tx, &pool, &channels, &claims.sub, &payload.ign).await {
+        Ok(message) => {
+            record_draft_action(&pool, &state, AuditEntry {
+                actor: &claims.sub,
+                action: "draft_pick",
+                target: Some(&payload.ign),
+                before: Some(before),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, message)
+        }
+        Err(e) => e,
+    }
+}
 
-    let turn = state_guard.current_turn;
-    let direction = state_guard.direction;
-    let teams = &mut state_guard.teams.0;
+/**
+ * Picks the player with the given IGN for the team on the clock. Shared by
+ * `POST /draft/pick` and the websocket `pick` command.
+ */
+pub async fn draft_pick_internal(
+    state: &SharedDraftState,
+    tx: &broadcast::Sender<ServerEvent>,
+    pool: &SqlitePool,
+    channels: &SharedChannelRegistry,
+    username: &str,
+    ign: &str,
+) -> Result<String, (StatusCode, String)> {
+    info!("Drafting player {}", ign);
+    let mut state_guard = state.write().await;
 
-    if turn >= teams.len() as i64 {
-        return (
+    if state_guard.phase != "Drafting" {
+        return Err((
             StatusCode::BAD_REQUEST,
-            format!("Invalid current turn: {}", turn),
-        )
+            format!("Cannot pick while the draft is {}.", state_guard.phase),
+        ));
     }
 
-    let current_team = &mut teams[turn as usize];
+    let mut updated = state_guard.clone();
+    let turn = updated.current_turn;
+    let direction = updated.direction;
+    let team_count = updated.teams.0.len();
 
-    if current_team.created_by != Some(claims.sub) {
-        return (StatusCode::UNAUTHORIZED, format!("You do not have permission to pick for this team."));
+    let current_team = usize::try_from(turn).ok()
+        .and_then(|index| updated.teams.0.get_mut(index))
+        .ok_or((StatusCode::BAD_REQUEST, format!("Invalid current turn: {}", turn)))?;
+
+    if current_team.created_by.as_deref() != Some(username) {
+        return Err((StatusCode::UNAUTHORIZED, "You do not have permission to pick for this team.".to_string()));
     }
 
     // Safely parse selections JSON string
@@ -229,97 +282,286 @@ pub async fn draft_pick(
     ).unwrap_or_default();
 
     if selections.len() >= 5 {
-        return (
+        return Err((
             StatusCode::BAD_REQUEST,
             format!("Team '{}' is full and cannot pick.", current_team.name),
-        )
+        ));
+    }
+
+    let mut player = match sqlx::query_as::<_, Player>("SELECT * FROM players WHERE ign = ?")
+        .bind(ign)
+        .fetch_optional(pool)
+        .await {
+            Ok(Some(player)) => player,
+            Ok(None) => {
+                return Err((StatusCode::NOT_FOUND, format!("Player '{}' was not found.", ign)));
+            }
+            Err(e) => {
+                error!("Failed to fetch player: {:?}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load player from database".to_string()));
+            }
+        };
+
+    if player.drafted {
+        return Err((StatusCode::CONFLICT, format!("Player '{}' has already been drafted.", player.ign)));
+    }
+
+    let announcement = format!("{} picked {}.", current_team.name, player.ign);
+
+    // push the selection in selections.
+    player.drafted = true;
+    let player_ign = player.ign.clone();
+    selections.push(player);
+    current_team.selections = match serde_json::to_string(&selections) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize selections: {}", e)));
+        }
+    };
+    updated.pick_history.0.push(PickRecord { turn, direction, ign: player_ign.clone() });
+
+    // Snake draft logic
+    let mut next_turn = turn as isize + direction as isize;
+    if next_turn >= team_count as isize {
+        next_turn = team_count as isize - 1;
+        updated.direction = -1;
+    } else if next_turn < 0 {
+        next_turn = 0;
+        updated.direction = 1;
     }
 
+    updated.current_turn = next_turn as i64;
+
+    /* The player row and the draft state change together or not at all */
+    let mut db = pool.begin().await.map_err(|e| {
+        error!("Failed to start pick transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update player status".to_string())
+    })?;
+
     let update_result = sqlx::query!(
         r#"
         UPDATE players
         SET drafted = 1
-        WHERE ign = ?
+        WHERE ign = ? AND drafted = 0
         "#,
-        payload.ign
+        player_ign
     )
-    .execute(&pool)
+    .execute(&mut *db)
     .await;
 
-    if let Err(e) = update_result {
-        error!("Failed to mark player as drafted: {:?}", e);
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update player status".to_string());
+    match update_result {
+        Ok(result) if result.rows_affected() == 0 => {
+            return Err((StatusCode::CONFLICT, format!("Player '{}' has already been drafted.", player_ign)));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to mark player as drafted: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update player status".to_string()));
+        }
     }
 
-    // push the selection in selections.
-    selections.push(payload);
-    let selections_json = match serde_json::to_string(&selections) {
-        Ok(json) => json,
+    save_draft_state(&mut *db, &updated).await?;
+
+    if let Err(e) = db.commit().await {
+        error!("Failed to commit pick: {:?}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save draft state".to_string()));
+    }
+
+    *state_guard = updated;
+
+    drop(state_guard);
+    send_draft_update(tx, state).await;
+    notify_on_the_clock(channels, state).await;
+    send_player_update(pool, tx).await;
+    post_system_message(pool, tx, &announcement).await;
+
+    Ok("Successfully pushed selection to team.".to_string())
+}
+
+/**
+ * Moves the draft between "Drafting" and "Paused". Picks are rejected while paused.
+ */
+pub async fn set_paused_internal(
+    state: &SharedDraftState,
+    tx: &broadcast::Sender<ServerEvent>,
+    pool: &SqlitePool,
+    channels: &SharedChannelRegistry,
+    paused: bool,
+) -> Result<String, (StatusCode, String)> {
+    let (action, from, to) = if paused {
+        ("pause", "Drafting", "Paused")
+    } else {
+        ("resume", "Paused", "Drafting")
+    };
+
+    let mut state_guard = state.write().await;
+
+    if state_guard.phase != from {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Cannot {} the draft while it is {}.", action, state_guard.phase),
+        ));
+    }
+
+    let mut updated = state_guard.clone();
+    updated.phase = to.to_string();
+    save_draft_state(pool, &updated).await?;
+    *state_guard = updated;
+
+    info!("Draft is now {}.", to);
+
+    drop(state_guard);
+    send_draft_update(tx, state).await;
+    notify_on_the_clock(channels, state).await;
+
+    Ok(format!("Draft is now {}.", to))
+}
+
+/**
+ * Reverts the most recent pick: the player goes back into the pool and the
+ * turn and snake direction return to what they were before the pick.
+ */
+pub async fn undo_pick_internal(
+    state: &SharedDraftState,
+    tx: &broadcast::Sender<ServerEvent>,
+    pool: &SqlitePool,
+    channels: &SharedChannelRegistry,
+) -> Result<String, (StatusCode, String)> {
+    let mut state_guard = state.write().await;
+
+    if state_guard.phase != "Drafting" && state_guard.phase != "Paused" {
+        return Err((StatusCode::BAD_REQUEST, "There is no draft in progress.".to_string()));
+    }
+
+    let mut updated = state_guard.clone();
+    let record = updated.pick_history.0.pop()
+        .ok_or((StatusCode::BAD_REQUEST, "There are no picks to undo.".to_string()))?;
+
+    let team = updated.teams.0.get_mut(record.turn as usize)
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, format!("No team found for turn {}", record.turn)))?;
+
+    let mut selections: Vec<Player> = serde_json::from_str(
+        team.selections.as_deref().unwrap_or("[]")
+    ).unwrap_or_default();
+
+    if let Some(index) = selections.iter().rposition(|p| p.ign == record.ign) {
+        selections.remove(index);
+    }
+
+    team.selections = match serde_json::to_string(&selections) {
+        Ok(json) => Some(json),
         Err(e) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize selections: {}", e));
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize selections: {}", e)));
         }
     };
-    current_team.selections = Some(selections_json);
 
-    // Snake draft logic
-    let mut next_turn = turn as isize + direction as isize;
-    if next_turn >= teams.len() as isize {
-        next_turn = teams.len() as isize - 1;
-        state_guard.direction = -1;
-    } else if next_turn < 0 {
-        next_turn = 0;
-        state_guard.direction = 1;
+    updated.current_turn = record.turn;
+    updated.direction = record.direction;
+
+    let mut db = pool.begin().await.map_err(|e| {
+        error!("Failed to start undo transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update player status".to_string())
+    })?;
+
+    let update_result = sqlx::query!(
+        r#"
+        UPDATE players
+        SET drafted = 0
+        WHERE ign = ?
+        "#,
+        record.ign
+    )
+    .execute(&mut *db)
+    .await;
+
+    if let Err(e) = update_result {
+        error!("Failed to return player to the pool: {:?}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update player status".to_string()));
+    }
+
+    save_draft_state(&mut *db, &updated).await?;
+
+    if let Err(e) = db.commit().await {
+        error!("Failed to commit undo: {:?}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save draft state".to_string()));
     }
 
-    state_guard.current_turn = next_turn as i64;
+    *state_guard = updated;
 
-    let teams_json = match serde_json::to_string(&state_guard.teams.0) {
+    info!("Undid the pick of {}.", record.ign);
+
+    drop(state_guard);
+    send_draft_update(tx, state).await;
+    notify_on_the_clock(channels, state).await;
+    send_player_update(pool, tx).await;
+    post_system_message(pool, tx, &format!("The pick of {} was undone.", record.ign)).await;
+
+    Ok(format!("Undid the pick of {}.", record.ign))
+}
+
+/**
+ * Writes the draft state row. Takes any executor so a pick can save it in the
+ * same transaction as the player it marks drafted.
+ */
+pub async fn save_draft_state<'e, E>(
+    db: E,
+    state: &DraftState,
+) -> Result<(), (StatusCode, String)>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let teams_json = match serde_json::to_string(&state.teams.0) {
         Ok(json) => json,
         Err(e) => {
             error!("Failed to serialize teams: {:?}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string())
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string()))
         }
     };
 
-    let drafted_players_json = match serde_json::to_string(&state_guard.drafted_players.0) {
+    let drafted_players_json = match serde_json::to_string(&state.drafted_players.0) {
         Ok(json) => json,
         Err(e) => {
             error!("Failed to serialize drafted players: {:?}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string())
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string()))
+        }
+    };
+
+    let pick_history_json = match serde_json::to_string(&state.pick_history.0) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize pick history: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string()))
         }
     };
 
-    // Save draft state
     let result = sqlx::query!(
         r#"
-        INSERT INTO draft_state (id, phase, teams, current_turn, drafted_players, direction)
-        VALUES (1, ?, ?, ?, ?, ?)
+        INSERT INTO draft_state (id, phase, teams, current_turn, drafted_players, direction, pick_history)
+        VALUES (1, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(id) DO UPDATE SET
             phase = excluded.phase,
             teams = excluded.teams,
             current_turn = excluded.current_turn,
             drafted_players = excluded.drafted_players,
-            direction = excluded.direction
+            direction = excluded.direction,
+            pick_history = excluded.pick_history
         "#,
-        state_guard.phase,
+        state.phase,
         teams_json,
-        state_guard.current_turn,
+        state.current_turn,
         drafted_players_json,
-        state_guard.direction
+        state.direction,
+        pick_history_json
     )
-    .execute(&pool)
+    .execute(db)
     .await;
 
     if let Err(e) = result {
         error!("Failed to save draft state: {:?}", e);
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save draft state".to_string());
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save draft state".to_string()));
     }
-    
-    drop(state_guard);
-    send_draft_update(&tx, &state).await;
-    send_player_update(&pool, &tx).await;
 
-    (StatusCode::OK, format!("Successfully pushed selection to team."))
+    Ok(())
 }
 
 pub async fn get_state(
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{ConnectInfo, Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::SqlitePool;
+use tracing::{info, error};
+use std::net::SocketAddr;
+
+use crate::dto::invite_dto::{CreateInvite, Invite};
+use crate::services::audit::{record, AuditEntry};
+use crate::services::invites::{create_invite, issue_player_invites};
+use crate::services::rbac::{require, Authorized, Role};
+
+/**
+ * GET all invites, newest first.
+ */
+pub async fn get_invites(
+    _: Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let invites = sqlx::query_as::<_, Invite>("SELECT * FROM invites ORDER BY created_at DESC")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch invites: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch invites")
+        })?;
+
+    Ok((StatusCode::OK, Json(invites)))
+}
+
+/**
+ * POST to create a single invite, optionally bound to a signup's IGN.
+ */
+pub async fn create_invites(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<CreateInvite>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    if let Some(ign) = &payload.player_ign {
+        let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM players WHERE ign = ?")
+            .bind(ign)
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to look up player: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to look up player")
+            })?;
+
+        if exists == 0 {
+            return Err((StatusCode::NOT_FOUND, "No signup with that IGN"));
+        }
+    }
+
+    let role = payload.role.unwrap_or(Role::Player);
+    let invite = create_invite(&pool, payload.player_ign.as_deref(), role, &claims.sub)
+        .await
+        .map_err(|e| {
+            error!("Failed to create invite: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create invite")
+        })?;
+
+    info!("{} created an invite for {:?}.", claims.sub, invite.player_ign);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "create_invite",
+        target: Some(&invite.code),
+        after: Some(format!("{:?} invite for {}", invite.role, invite.player_ign.as_deref().unwrap_or("anyone"))),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+    Ok((StatusCode::OK, Json(invite)))
+}
+
+/**
+ * POST to issue an invite for every imported signup that does not have one yet.
+ */
+pub async fn issue_signup_invites(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let invites = issue_player_invites(&pool, &claims.sub)
+        .await
+        .map_err(|e| {
+            error!("Failed to issue signup invites: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to issue invites")
+        })?;
+
+    info!("{} issued {} signup invites.", claims.sub, invites.len());
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "issue_signup_invites",
+        after: Some(format!("{} invites", invites.len())),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+    Ok((StatusCode::OK, Json(invites)))
+}
+
+/**
+ * DELETE an unused invite.
+ */
+pub async fn delete_invite(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    let result = sqlx::query("DELETE FROM invites WHERE code = ? AND used_by IS NULL")
+        .bind(&code)
+        .execute(&pool)
+        .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => (StatusCode::NOT_FOUND, "Unused invite was not found.".to_string()),
+        Ok(_) => {
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "delete_invite",
+                target: Some(&code),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, "Invite was revoked.".to_string())
+        }
+        Err(e) => {
+            error!("Failed to delete invite: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete invite: {}", e))
+        }
+    }
+}
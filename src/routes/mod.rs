@@ -1,4 +1,10 @@
 pub mod teams;
 pub mod users;
 pub mod players;
-pub mod draft;
\ No newline at end of file
+pub mod draft;
+pub mod chat;
+pub mod invites;
+pub mod player_claims;
+pub mod oauth;
+pub mod audit;
+pub mod tournament;
\ No newline at end of file
@@ -0,0 +1,297 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header::SET_COOKIE, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tracing::{info, error, warn};
+
+use crate::dto::oauth_dto::{OAuthAuthorizeQuery, OAuthCallback};
+use crate::dto::user_dto::User;
+use crate::services::auth_user::AuthUser;
+use crate::services::invites::redeem_invite;
+use crate::services::jwt_keys::SharedJwtKeys;
+use crate::services::oauth::{create_state, take_state, ExternalIdentity, SharedOAuthProviders};
+use crate::services::password::hash_password;
+use crate::services::sessions::{issue_session, random_token};
+use crate::services::validation::{validate_username, Valid};
+
+type JsonError = (StatusCode, Json<Value>);
+
+fn json_error(status: StatusCode, message: &str) -> JsonError {
+    (status, Json(json!({"error": message})))
+}
+
+fn database_error(e: sqlx::Error) -> JsonError {
+    error!("OAuth database error: {:?}", e);
+    json_error(StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.")
+}
+
+/**
+ * GET the provider URL to send the browser to for signing in. First-time users
+ * pass `?invite_code=` so an account can be created for them. Also sets the
+ * state cookie the callback checks, so the request must send credentials.
+ */
+pub async fn oauth_authorize(
+    Path(provider): Path<String>,
+    Query(query): Query<OAuthAuthorizeQuery>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(providers): Extension<SharedOAuthProviders>,
+) -> Result<impl IntoResponse, JsonError> {
+    let config = providers
+        .get(&provider)
+        .ok_or(json_error(StatusCode::NOT_FOUND, "Unknown login provider."))?;
+
+    let state = create_state(&pool, &provider, query.invite_code.as_deref(), None)
+        .await
+        .map_err(database_error)?;
+
+    Ok((StatusCode::OK, [(SET_COOKIE, providers.state_cookie(&state))], Json(json!({
+        "url": config.authorize_url(&state),
+        "state": state
+    }))))
+}
+
+/**
+ * GET the provider URL for linking a provider login to the signed-in account.
+ * Sets the state cookie like `/authorize`.
+ */
+pub async fn oauth_link(
+    AuthUser(claims): AuthUser,
+    Path(provider): Path<String>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(providers): Extension<SharedOAuthProviders>,
+) -> Result<impl IntoResponse, JsonError> {
+    let config = providers
+        .get(&provider)
+        .ok_or(json_error(StatusCode::NOT_FOUND, "Unknown login provider."))?;
+
+    let state = create_state(&pool, &provider, None, Some(&claims.sub))
+        .await
+        .map_err(database_error)?;
+
+    Ok((StatusCode::OK, [(SET_COOKIE, providers.state_cookie(&state))], Json(json!({
+        "url": config.authorize_url(&state),
+        "state": state
+    }))))
+}
+
+/**
+ * POST the `code` and `state` the provider redirected back with. Signs in the
+ * linked account, links the login to the account that started it, or creates
+ * an account from the invite given when the login started. Responds like `/login`.
+ * The state cookie set when the login started must come back with it, so a
+ * state handed to someone else's browser can't complete the login there.
+ */
+pub async fn oauth_callback(
+    Path(provider): Path<String>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(providers): Extension<SharedOAuthProviders>,
+    Extension(keys): Extension<SharedJwtKeys>,
+    headers: HeaderMap,
+    Valid(payload): Valid<OAuthCallback>,
+) -> Result<impl IntoResponse, JsonError> {
+    let config = providers
+        .get(&provider)
+        .ok_or(json_error(StatusCode::NOT_FOUND, "Unknown login provider."))?;
+
+    if !providers.has_state_cookie(&headers, &payload.state) {
+        warn!("{} login state came back without its cookie.", provider);
+        return Err(json_error(StatusCode::BAD_REQUEST, "This login was started in another browser."));
+    }
+    let clear_cookie = [(SET_COOKIE, providers.clear_state_cookie())];
+
+    let pending = take_state(&pool, &provider, &payload.state)
+        .await
+        .map_err(database_error)?
+        .ok_or(json_error(StatusCode::BAD_REQUEST, "Invalid or expired login state."))?;
+
+    let identity = providers.fetch_identity(config, &payload.code).await.map_err(|e| {
+        warn!("{} login failed: {}", provider, e);
+        json_error(StatusCode::BAD_GATEWAY, "Could not verify the login with the provider.")
+    })?;
+
+    if let Some(username) = pending.link_username {
+        link_identity(&pool, &provider, &identity, &username).await?;
+        info!("{} linked a {} login.", username, provider);
+        return Ok((StatusCode::OK, clear_cookie, Json(json!({"message": format!("Linked your {} login.", provider)}))));
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        SELECT users.* FROM users
+        JOIN user_identities ON user_identities.user_id = users.id
+        WHERE user_identities.provider = ? AND user_identities.subject = ?
+        "#
+    )
+    .bind(&provider)
+    .bind(&identity.subject)
+    .fetch_optional(&pool)
+    .await
+    .map_err(database_error)?;
+
+    let user = match (user, pending.invite_code) {
+        (Some(user), _) => user,
+        (None, Some(invite_code)) => register_identity(&pool, &provider, &identity, &invite_code).await?,
+        (None, None) => {
+            return Err(json_error(
+                StatusCode::FORBIDDEN,
+                "No account uses this login yet. Sign in with an invite code to create one.",
+            ));
+        }
+    };
+
+    let session = issue_session(&pool, &keys, &user)
+        .await
+        .map_err(|(status, e)| json_error(status, &e))?;
+
+    info!("{} signed in with {}.", user.username, provider);
+    Ok((StatusCode::OK, clear_cookie, Json(json!({
+        "name": user.name,
+        "token": session.token,
+        "refresh_token": session.refresh_token,
+        "expires_in": session.expires_in
+    }))))
+}
+
+async fn link_identity(
+    pool: &SqlitePool,
+    provider: &str,
+    identity: &ExternalIdentity,
+    username: &str,
+) -> Result<(), JsonError> {
+    let created_at = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, created_at)
+        SELECT ?, ?, id, ? FROM users WHERE username = ?
+        "#
+    )
+    .bind(provider)
+    .bind(&identity.subject)
+    .bind(created_at)
+    .bind(username)
+    .execute(pool)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => Err(json_error(StatusCode::NOT_FOUND, "User was not found.")),
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(json_error(
+            StatusCode::CONFLICT,
+            "That login is already linked to an account.",
+        )),
+        Err(e) => Err(database_error(e)),
+    }
+}
+
+/**
+ * Creates an account for a first-time provider login, the same way `/users`
+ * does with an invite. The account gets an unguessable password, so it can
+ * only sign in through the provider until the password is reset.
+ */
+async fn register_identity(
+    pool: &SqlitePool,
+    provider: &str,
+    identity: &ExternalIdentity,
+    invite_code: &str,
+) -> Result<User, JsonError> {
+    let username = available_username(pool, &identity.username).await?;
+    let password_hash = hash_password(&random_token()).map_err(|e| {
+        error!("Failed to hash password: {:?}", e);
+        json_error(StatusCode::INTERNAL_SERVER_ERROR, "Could not create user.")
+    })?;
+
+    let mut tx = pool.begin().await.map_err(database_error)?;
+
+    let invite = redeem_invite(&mut tx, invite_code, &username)
+        .await
+        .map_err(database_error)?
+        .ok_or(json_error(StatusCode::FORBIDDEN, "Invalid or already used invite code."))?;
+
+    let (ign, ign_verified) = match invite.player_ign {
+        Some(player_ign) => (player_ign, true),
+        None => (identity.username.clone(), false),
+    };
+
+    let sessions_valid_after = Utc::now().timestamp_millis();
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (team_id, name, username, ign, password, role, ign_verified, sessions_valid_after, player_ign)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, (SELECT ign FROM players WHERE ign = ? AND ?))
+        RETURNING *
+        "#
+    )
+    .bind(None::<i64>)
+    .bind(&identity.display_name)
+    .bind(&username)
+    .bind(&ign)
+    .bind(password_hash)
+    .bind(invite.role)
+    .bind(ign_verified)
+    .bind(sessions_valid_after)
+    .bind(&ign)
+    .bind(ign_verified)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, created_at)
+        VALUES (?, ?, ?, ?)
+        "#
+    )
+    .bind(provider)
+    .bind(&identity.subject)
+    .bind(user.id)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    info!("Created {} from a {} login.", username, provider);
+    Ok(user)
+}
+
+/**
+ * The provider's username, or the first free `name-2`, `name-3`, ... if taken.
+ * It has to pass the same rules as a username picked at sign up.
+ */
+async fn available_username(pool: &SqlitePool, wanted: &str) -> Result<String, JsonError> {
+    if !(3..=32).contains(&wanted.len()) || validate_username(wanted).is_err() {
+        return Err(json_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Your login's username can't be used here. It must be 3 to 32 letters, digits, '_', '-' or '.'.",
+        ));
+    }
+
+    for n in 1..100 {
+        let candidate = if n == 1 {
+            wanted.to_string()
+        } else {
+            let suffix = format!("-{}", n);
+            // Checked to be ASCII above, so any byte index is a char boundary
+            format!("{}{}", &wanted[..wanted.len().min(32 - suffix.len())], suffix)
+        };
+
+        let taken = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE username = ?")
+            .bind(&candidate)
+            .fetch_one(pool)
+            .await
+            .map_err(database_error)?;
+
+        if taken == 0 {
+            return Ok(candidate);
+        }
+    }
+
+    Err(json_error(StatusCode::CONFLICT, "Could not find a free username."))
+}
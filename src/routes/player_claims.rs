@@ -0,0 +1,189 @@
+use axum::{
+    extract::{ConnectInfo, Extension, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tracing::{info, error};
+use std::net::SocketAddr;
+
+use crate::dto::player_dto::{ClaimPlayer, PlayerClaim, PlayerClaimQuery, ReviewClaim};
+use crate::services::audit::{record, AuditEntry};
+use crate::services::auth_user::AuthUser;
+use crate::services::rbac::{require, Authorized};
+use crate::services::validation::Valid;
+
+fn database_error(e: sqlx::Error) -> (StatusCode, &'static str) {
+    error!("Player claim database error: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.")
+}
+
+/**
+ * POST to ask for your account to be linked to a signup. An admin has to approve
+ * the claim before the link takes effect.
+ */
+pub async fn claim_player(
+    AuthUser(claims): AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Valid(payload): Valid<ClaimPlayer>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let ign = payload.ign.trim();
+
+    let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM players WHERE ign = ?")
+        .bind(ign)
+        .fetch_one(&pool)
+        .await
+        .map_err(database_error)?;
+
+    if exists == 0 {
+        return Err((StatusCode::NOT_FOUND, "No signup with that IGN"));
+    }
+
+    let (already_linked, own_link) = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM users WHERE player_ign = ?),
+            (SELECT COUNT(*) FROM users WHERE username = ? AND player_ign IS NOT NULL)
+        "#
+    )
+    .bind(ign)
+    .bind(&claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(database_error)?;
+
+    if own_link > 0 {
+        return Err((StatusCode::CONFLICT, "Your account is already linked to a signup"));
+    }
+
+    if already_linked > 0 {
+        return Err((StatusCode::CONFLICT, "That signup is already linked to another account"));
+    }
+
+    let pending = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM player_claims WHERE username = ? AND status = 'pending'"
+    )
+    .bind(&claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(database_error)?;
+
+    if pending > 0 {
+        return Err((StatusCode::CONFLICT, "You already have a claim waiting for review"));
+    }
+
+    let created_at = Utc::now().to_rfc3339();
+    let claim = sqlx::query_as::<_, PlayerClaim>(
+        r#"
+        INSERT INTO player_claims (username, player_ign, status, created_at)
+        VALUES (?, ?, 'pending', ?)
+        RETURNING *
+        "#
+    )
+    .bind(&claims.sub)
+    .bind(ign)
+    .bind(created_at)
+    .fetch_one(&pool)
+    .await
+    .map_err(database_error)?;
+
+    info!("{} claimed signup {}.", claims.sub, ign);
+    Ok((StatusCode::OK, Json(claim)))
+}
+
+/**
+ * GET player claims, newest first, optionally filtered by `?status=pending`.
+ */
+pub async fn get_player_claims(
+    _: Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<PlayerClaimQuery>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let claims = sqlx::query_as::<_, PlayerClaim>(
+        r#"
+        SELECT * FROM player_claims
+        WHERE ? IS NULL OR status = ?
+        ORDER BY id DESC
+        "#
+    )
+    .bind(&query.status)
+    .bind(&query.status)
+    .fetch_all(&pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok((StatusCode::OK, Json(claims)))
+}
+
+/**
+ * POST to approve or reject a pending claim. Approving links the account to
+ * the signup and marks its IGN as verified.
+ */
+pub async fn review_player_claim(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(claim_id): Path<i64>,
+    Json(payload): Json<ReviewClaim>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let status = if payload.approve { "approved" } else { "rejected" };
+    let reviewed_at = Utc::now().to_rfc3339();
+
+    let mut tx = pool.begin().await.map_err(database_error)?;
+
+    let claim = sqlx::query_as::<_, PlayerClaim>(
+        r#"
+        UPDATE player_claims SET status = ?, reviewed_by = ?, reviewed_at = ?
+        WHERE id = ? AND status = 'pending'
+        RETURNING *
+        "#
+    )
+    .bind(status)
+    .bind(&claims.sub)
+    .bind(reviewed_at)
+    .bind(claim_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(database_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Pending claim was not found."))?;
+
+    if payload.approve {
+        let linked = sqlx::query(
+            r#"
+            UPDATE users SET player_ign = ?, ign = ?, ign_verified = 1
+            WHERE username = ? AND player_ign IS NULL
+            "#
+        )
+        .bind(&claim.player_ign)
+        .bind(&claim.player_ign)
+        .bind(&claim.username)
+        .execute(&mut *tx)
+        .await;
+
+        match linked {
+            Ok(res) if res.rows_affected() == 0 => {
+                return Err((StatusCode::CONFLICT, "That account no longer exists or is already linked."));
+            }
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err((StatusCode::CONFLICT, "That signup is already linked to another account."));
+            }
+            Err(e) => return Err(database_error(e)),
+        }
+    }
+
+    tx.commit().await.map_err(database_error)?;
+
+    info!("{} {} the claim of {} on {}.", claims.sub, status, claim.username, claim.player_ign);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "review_player_claim",
+        target: Some(&claim.username),
+        before: Some(format!("pending claim on {}", claim.player_ign)),
+        after: Some(status.to_string()),
+        ip: Some(addr.ip()),
+    }).await;
+    Ok((StatusCode::OK, Json(claim)))
+}
@@ -1,95 +1,356 @@
 use axum::{
-    extract::{Extension},
+    extract::{ConnectInfo, Extension, Query},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
-use sqlx::{pool, SqlitePool};
-use yup_oauth2::{ServiceAccountAuthenticator, read_service_account_key};
-use reqwest::Client;
-use serde_json::{Value};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::net::SocketAddr;
+use tokio::sync::broadcast;
+use tracing::{info, error};
+
+use crate::{dto::player_dto::{PlayerCard, Player, PlayerQuery, PlayerSort, SortOrder}, services::draft_player_formatter};
+use crate::dto::import_dto::{FormattedSignups, ImportCommit};
+use crate::dto::ws_dto::ServerEvent;
+use crate::services::audit::{record, AuditEntry};
+use crate::services::game_profiles::load_game_profile;
+use crate::services::player_import::{diff_signups, discard_preview, load_preview, store_preview};
+use crate::services::player_sources::fetch_signup_rows;
+use crate::services::rbac::{require, Authorized};
+use crate::services::validation::{FieldProblem, Invalid};
+use crate::services::websocket::send_player_update;
+
+/*
+ * Player filters shared by the list and its count. Roles from older imports
+ * may use any separator the signup did, so they are split on all of them and
+ * compared without spaces, like `role_key` does to the requested role.
+ */
+const PLAYER_FILTERS: &str = r#"
+    WHERE (?1 IS NULL OR drafted = ?1)
+      AND (?2 IS NULL OR name LIKE ?2 ESCAPE '\' OR ign LIKE ?2 ESCAPE '\')
+      AND (?3 IS NULL OR
+           ',' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+               IFNULL(roles, ''), ' ', ''), '/', ','), ';', ','), '&', ','), '|', ','), char(10), ',') || ','
+           LIKE '%,' || ?3 || ',%' ESCAPE '\')
+      AND (?4 IS NULL OR current_rank_order >= ?4)
+      AND (?5 IS NULL OR current_rank_order <= ?5)
+      AND (?6 IS NULL OR skill_rating >= ?6)
+      AND (?7 IS NULL OR skill_rating <= ?7)
+"#;
+
+/// Escapes `\`, `%` and `_` so a value matches literally in `LIKE ... ESCAPE '\'`.
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// A role as `PLAYER_FILTERS` compares it: without spaces, escaped for `LIKE`.
+fn role_key(role: &str) -> String {
+    escape_like(&role.split_whitespace().collect::<String>())
+}
+
+/**
+ * Players matching `query`, sorted and paged, along with how many matched
+ * before paging. Without a `limit` every match is returned.
+ */
+pub async fn search_players(pool: &SqlitePool, query: &PlayerQuery) -> Result<(Vec<Player>, i64), sqlx::Error> {
+    let descending = match query.order {
+        Some(order) => matches!(order, SortOrder::Desc),
+        None => !matches!(query.sort, PlayerSort::Name | PlayerSort::Ign),
+    };
+    let direction = if descending { "DESC" } else { "ASC" };
+
+    let order_by = match query.sort {
+        PlayerSort::SkillRating => format!("skill_rating {0}, current_rank_order {0}", direction),
+        PlayerSort::CurrentRank => format!("current_rank_order {0}, skill_rating {0}", direction),
+        PlayerSort::PeakRank => format!("peak_rank_order {0}, skill_rating {0}", direction),
+        PlayerSort::Name => format!("name COLLATE NOCASE {}", direction),
+        PlayerSort::Ign => format!("ign COLLATE NOCASE {}", direction),
+    };
+
+    let pattern = query.search.as_ref().map(|search| format!("%{}%", escape_like(search.trim())));
+    let role = query.role.as_deref().map(role_key);
+    let limit = query.limit.map_or(-1, |limit| limit.clamp(1, 500));
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    // IGN last so pages stay stable when players tie
+    let players = sqlx::query_as::<_, Player>(&format!(
+        "SELECT * FROM players {} ORDER BY {}, ign LIMIT ?8 OFFSET ?9",
+        PLAYER_FILTERS, order_by
+    ))
+    .bind(query.drafted)
+    .bind(&pattern)
+    .bind(&role)
+    .bind(query.min_rank)
+    .bind(query.max_rank)
+    .bind(query.min_rating)
+    .bind(query.max_rating)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM players {}", PLAYER_FILTERS))
+        .bind(query.drafted)
+        .bind(&pattern)
+        .bind(&role)
+        .bind(query.min_rank)
+        .bind(query.max_rank)
+        .bind(query.min_rating)
+        .bind(query.max_rating)
+        .fetch_one(pool)
+        .await?;
+
+    Ok((players, total))
+}
 
-use crate::{dto::player_dto::{PlayerCard, Player}, services::draft_player_formatter};
 /**
- * GET the players that signed up for the tournament.
+ * GET the players that signed up for the tournament, highest skill rating
+ * first. Filter with `drafted`, `search` (name or IGN), `role`,
+ * `min_rank`/`max_rank` and `min_rating`/`max_rating`; sort with `sort`
+ * (`skill_rating`, `current_rank`, `peak_rank`, `name` or `ign`) and `order`;
+ * page with `limit` and `offset`. The number of matches before paging is in
+ * the `X-Total-Count` header.
  */
 pub async fn get_players(
-    Extension(pool): Extension<SqlitePool>
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<PlayerQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
-    let service_account_key = read_service_account_key("credentials.json")
+    let (players, total) = search_players(&pool, &query)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Could not read Google credentials"))?;
+        .map_err(|e| {
+            error!("Failed to search players: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch players from DB")
+        })?;
+
+    Ok((StatusCode::OK, [("x-total-count", total.to_string())], Json(players)))
+}
 
-    let auth = ServiceAccountAuthenticator::builder(service_account_key)
-        .build()
+/// Reads and formats the signups from the tournament's player source.
+async fn read_signups(pool: &SqlitePool) -> Result<FormattedSignups, (StatusCode, &'static str)> {
+    let (values, mapping) = fetch_signup_rows(pool)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create Google auth"))?;
+        .map_err(|e| {
+            error!("Failed to read signups: {}", e);
+            (StatusCode::BAD_GATEWAY, "Could not read signups from the player source")
+        })?;
 
-    let token = auth.token(&["https://www.googleapis.com/auth/spreadsheets.readonly"])
+    let profile = load_game_profile(pool)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Google API token error"))?;
+        .map_err(|e| {
+            error!("Failed to load game profile: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not load the tournament's game profile")
+        })?;
 
-    let url = format!(
-        "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}",
-        "1_57KqAux4swU4QAdQXeEd--eDDSFZzF_FXVosagzAQU", "Form Responses 1"
-    );
+    Ok(draft_player_formatter::format_responses(&values, &mapping, &profile))
+}
 
-    let client = Client::new();
-    let response: Value = client
-        .get(&url)
-        .bearer_auth(token.token().ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing token string"))?)
-        .send()
+async fn load_players(pool: &SqlitePool) -> Result<Vec<Player>, (StatusCode, &'static str)> {
+    sqlx::query_as::<_, Player>("SELECT * FROM players ORDER BY skill_rating DESC")
+        .fetch_all(pool)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to send request"))?
-        .json()
+        .map_err(|e| {
+            error!("Failed to load players: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch players from DB")
+        })
+}
+
+/**
+ * POST to import signups from the tournament's player source into the player
+ * pool. Admins only. Clients are sent a `player_update` if anything changed.
+ * Rows that could not be read are listed under `problems`.
+ */
+pub async fn import_players(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let FormattedSignups { players, problems } = read_signups(&pool).await?;
+
+    let save_error = |_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save players to DB");
+    let mut db = pool.begin().await.map_err(save_error)?;
+    let changed = save_players(&mut db, &players).await.map_err(save_error)?;
+    db.commit().await.map_err(save_error)?;
+
+    info!("{} imported {} signups, {} new or changed.", claims.sub, players.len(), changed);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "import_players",
+        after: Some(format!("{} signups, {} new or changed", players.len(), changed)),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
+    if changed > 0 {
+        send_player_update(&pool, &tx).await;
+    }
+
+    Ok((StatusCode::OK, Json(serde_json::json!({
+        "signups": players.len(),
+        "changed": changed,
+        "problems": problems
+    }))))
+}
+
+/**
+ * POST to read signups from the player source without saving them. Returns the
+ * new players, the fields that would change for existing players and the
+ * players no longer in the source, along with a `preview_id` to commit and
+ * any rows of the signup table that could not be read.
+ */
+pub async fn preview_import(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let FormattedSignups { players: signups, problems } = read_signups(&pool).await?;
+    let existing = load_players(&pool).await?;
+
+    let preview_id = store_preview(&pool, &claims.sub, &signups)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to parse JSON response"))?;
-
-    let values = response.get("values")
-        .and_then(|v| v.as_array())
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No values array found in response"))?;
-
-    let players = draft_player_formatter::format_responses(values);
-
-    save_players(&pool, &players).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save players to DB"))?;
-
-    let saved_players = sqlx::query_as!(
-        Player,
-        r#"
-        SELECT
-            name,
-            peak_rank,
-            current_rank,
-            teammate_preferences,
-            roles,
-            ign,
-            current_rank_order,
-            peak_rank_order,
-            drafted
-        FROM players
-        ORDER BY current_rank_order DESC
-        "#
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch players from DB"))?;
-
-    Ok((StatusCode::OK, Json(saved_players)))
+        .map_err(|e| {
+            error!("Failed to store import preview: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not store the import preview")
+        })?;
+
+    let mut diff = diff_signups(preview_id, &existing, &signups);
+    diff.problems = problems;
+    info!(
+        "{} previewed an import: {} new, {} changed, {} missing.",
+        claims.sub, diff.new_players.len(), diff.changed.len(), diff.missing.len()
+    );
+
+    Ok((StatusCode::OK, Json(diff)))
 }
 
+/**
+ * POST to apply a previewed import. `apply` lists the IGNs of new or changed
+ * players to write (all of them if omitted) and `remove` lists undrafted
+ * players missing from the source to drop from the pool, e.g.
+ * `{"preview_id": 3, "apply": ["Alice#NA1"], "remove": ["Bob#EUW"]}`.
+ */
+pub async fn commit_import(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<ImportCommit>,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let signups = load_preview(&pool, payload.preview_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load import preview: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not load the import preview")
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Import preview was not found or has expired"))?;
+
+    // Diff again so anything that changed since the preview is not overwritten blindly
+    let existing = load_players(&pool).await?;
+    let diff = diff_signups(payload.preview_id, &existing, &signups);
+
+    let pending: Vec<&PlayerCard> = signups
+        .iter()
+        .filter(|card| {
+            diff.new_players.iter().any(|p| p.ign == card.ign) || diff.changed.iter().any(|c| c.ign == card.ign)
+        })
+        .collect();
+
+    let mut problems = vec![];
+
+    if let Some(apply) = &payload.apply {
+        for ign in apply.iter().filter(|ign| !pending.iter().any(|card| &&card.ign == ign)) {
+            problems.push(FieldProblem {
+                field: "apply".to_string(),
+                problem: format!("{} is not a new or changed signup in this preview", ign),
+            });
+        }
+    }
+
+    for ign in &payload.remove {
+        match diff.missing.iter().find(|player| &player.ign == ign) {
+            Some(player) if player.drafted => problems.push(FieldProblem {
+                field: "remove".to_string(),
+                problem: format!("{} has already been drafted", ign),
+            }),
+            Some(_) => {}
+            None => problems.push(FieldProblem {
+                field: "remove".to_string(),
+                problem: format!("{} is not a player missing from the source", ign),
+            }),
+        }
+    }
+
+    if !problems.is_empty() {
+        return Ok(Invalid(problems).into_response());
+    }
+
+    let selected: Vec<PlayerCard> = pending
+        .into_iter()
+        .filter(|card| payload.apply.as_ref().is_none_or(|apply| apply.contains(&card.ign)))
+        .cloned()
+        .collect();
+
+    // Saving and removing land together; the preview stays usable if either fails
+    let save_error = |_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save players to DB");
+    let mut db = pool.begin().await.map_err(save_error)?;
+    let changed = save_players(&mut db, &selected).await.map_err(save_error)?;
+
+    let mut removed = 0;
+    for ign in &payload.remove {
+        let result = sqlx::query("DELETE FROM players WHERE ign = ? AND drafted = 0")
+            .bind(ign)
+            .execute(&mut *db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to remove players from DB"))?;
+
+        removed += result.rows_affected();
+    }
+
+    db.commit().await.map_err(save_error)?;
+
+    if let Err(e) = discard_preview(&pool, payload.preview_id).await {
+        error!("Failed to discard import preview {}: {:?}", payload.preview_id, e);
+    }
+
+    info!("{} committed import preview {}: {} saved, {} removed.", claims.sub, payload.preview_id, changed, removed);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "commit_player_import",
+        after: Some(format!("{} new or changed, {} removed ({})", changed, removed, payload.remove.join(", "))),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
+    if changed > 0 || removed > 0 {
+        send_player_update(&pool, &tx).await;
+    }
+
+    Ok((StatusCode::OK, Json(serde_json::json!({
+        "changed": changed,
+        "removed": removed
+    }))).into_response())
+}
+
+/**
+ * Upserts signups by IGN, leaving `drafted` alone. Returns how many players
+ * were added or had a field change.
+ */
 pub async fn save_players(
-    pool: &SqlitePool,
+    db: &mut SqliteConnection,
     players: &[PlayerCard],
-) -> Result<(), sqlx::Error> {
+) -> Result<u64, sqlx::Error> {
+    let mut changed = 0;
+
     for player in players {
-        sqlx::query!(
+        let attributes = serde_json::to_string(&player.attributes).unwrap_or_else(|_| "{}".to_string());
+
+        let result = sqlx::query!(
             r#"
             INSERT INTO players (
                 name, peak_rank, current_rank, teammate_preferences,
-                roles, ign, current_rank_order, peak_rank_order, drafted
+                roles, ign, current_rank_order, peak_rank_order, drafted, attributes,
+                skill_rating
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(ign) DO UPDATE SET
                 name = excluded.name,
                 peak_rank = excluded.peak_rank,
@@ -97,7 +358,18 @@ pub async fn save_players(
                 teammate_preferences = excluded.teammate_preferences,
                 roles = excluded.roles,
                 current_rank_order = excluded.current_rank_order,
-                peak_rank_order = excluded.peak_rank_order
+                peak_rank_order = excluded.peak_rank_order,
+                attributes = excluded.attributes,
+                skill_rating = excluded.skill_rating
+            WHERE name IS NOT excluded.name
+               OR peak_rank IS NOT excluded.peak_rank
+               OR current_rank IS NOT excluded.current_rank
+               OR teammate_preferences IS NOT excluded.teammate_preferences
+               OR roles IS NOT excluded.roles
+               OR current_rank_order IS NOT excluded.current_rank_order
+               OR peak_rank_order IS NOT excluded.peak_rank_order
+               OR attributes IS NOT excluded.attributes
+               OR skill_rating IS NOT excluded.skill_rating
             "#,
             player.name,
             player.peak_rank,
@@ -108,10 +380,14 @@ pub async fn save_players(
             player.current_rank_order,
             player.peak_rank_order,
             false,
+            attributes,
+            player.skill_rating,
         )
-        .execute(pool)
+        .execute(&mut *db)
         .await?;
+
+        changed += result.rows_affected();
     }
 
-    Ok(())
+    Ok(changed)
 }
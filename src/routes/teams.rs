@@ -1,15 +1,21 @@
 use axum::{
-    extract::{Extension, Path},
+    extract::{ConnectInfo, Extension, Path},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
 use sqlx::{SqlitePool};
 use tokio::sync::broadcast;
 use tracing::{info, error, warn};
+use std::net::SocketAddr;
 use crate::{dto::{player_dto::Player, team_dto::{CreateTeam, Team}}, services::websocket::send_player_update};
-use crate::services::websocket::{send_team_update};
+use crate::dto::{claims_dto::Claims, draft_dto::SharedDraftState, ws_dto::ServerEvent};
+use crate::routes::draft::save_draft_state;
+use crate::services::websocket::{send_draft_update, send_team_update};
+use crate::services::audit::{record, AuditEntry};
+use crate::services::validation::{Invalid, Valid};
 use crate::services::auth_user::AuthUser;
+use crate::services::rbac::{require, Authorized, Permission};
 /**
  * GET request to get all the teams.
  */
@@ -34,11 +40,27 @@ pub async fn get_teams(Extension(pool): Extension<SqlitePool>,) -> impl IntoResp
  */
 pub async fn create_teams(
     Extension(pool): Extension<SqlitePool>,
-    Extension(tx): Extension<broadcast::Sender<String>>,
-    AuthUser(claims): AuthUser,
-    Json(payload): Json<CreateTeam>,
-) -> impl IntoResponse {
-    info!("Creating a team {}", payload.name);
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Authorized(claims, _): Authorized<require::CreateTeam>,
+    Valid(payload): Valid<CreateTeam>,
+) -> Response {
+    let name = payload.name.trim();
+    info!("Creating a team {}", name);
+
+    let name_taken = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM teams WHERE name = ? COLLATE NOCASE")
+        .bind(name)
+        .fetch_one(&pool)
+        .await;
+
+    match name_taken {
+        Ok(0) => {}
+        Ok(_) => return Invalid::field("name", "is already taken by another team").into_response(),
+        Err(e) => {
+            error!("Failed to check team name: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Could not create the team".to_string()).into_response();
+        }
+    }
 
     let selections_json = match serde_json::to_string(&payload.selections) {
         Ok(json) => json,
@@ -47,7 +69,7 @@ pub async fn create_teams(
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Could not read selection input correctly: {}", e),
-            );
+            ).into_response();
         }
     };
 
@@ -57,7 +79,7 @@ pub async fn create_teams(
         INSERT INTO teams (name, selections, team_size, team_money, is_picking, created_by)
         VALUES (?, ?, ?, ?, ?, ?)
         "#,
-        payload.name,
+        name,
         selections_json,
         0,
         0,
@@ -67,22 +89,31 @@ pub async fn create_teams(
     .execute(&pool)
     .await
     {
+        // Another request took the name between the check above and the insert
+        if let sqlx::Error::Database(db_error) = &e
+            && db_error.is_unique_violation()
+        {
+            return Invalid::field("name", "is already taken by another team").into_response();
+        }
+
         error!("Failed to create team: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Could not create the team {}", payload.name),
-        );
+            format!("Could not create the team {}", name),
+        ).into_response();
     }
 
     let username = claims.sub;
-    let like_pattern = format!("{}%", username);
 
-    // Try to find a matching player
-    match sqlx::query_as!(
-        Player,
-        r#"SELECT * FROM players WHERE ign LIKE ? LIMIT 1"#,
-        like_pattern
+    // Find the captain's own player through their account link
+    match sqlx::query_as::<_, Player>(
+        r#"
+        SELECT players.* FROM players
+        JOIN users ON users.player_ign = players.ign
+        WHERE users.username = ?
+        "#
     )
+    .bind(&username)
     .fetch_optional(&pool)
     .await
     {
@@ -113,7 +144,7 @@ pub async fn create_teams(
                             return (
                                 StatusCode::INTERNAL_SERVER_ERROR,
                                 format!("Failed to serialize selections: {}", e),
-                            );
+                            ).into_response();
                         }
                     };
 
@@ -134,7 +165,7 @@ pub async fn create_teams(
                         return (
                             StatusCode::INTERNAL_SERVER_ERROR,
                             "Failed to update team with new selection".to_string(),
-                        );
+                        ).into_response();
                     }
 
                     let _ = sqlx::query!(
@@ -149,61 +180,148 @@ pub async fn create_teams(
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         "Could not fetch the created team".to_string(),
-                    );
+                    ).into_response();
                 }
             }
         }
         Ok(None) => {
-            warn!("No matching player found for '{}'", username);
+            warn!("No linked player found for '{}'", username);
         }
         Err(e) => {
             error!("Error fetching player: {}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to find matching player".to_string(),
-            );
+            ).into_response();
         }
     }
 
+    record(&pool, AuditEntry {
+        actor: &username,
+        action: "create_team",
+        target: Some(name),
+        after: Some(format!("owned by {}", username)),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
     send_team_update(&pool, &tx).await;
     send_player_update(&pool, &tx).await;
     (
         StatusCode::OK,
-        format!("Successfully created the team!"),
-    )
+        "Successfully created the team!".to_string(),
+    ).into_response()
 }
 
 /**
- * DELETE request to delete a team by their name.
+ * DELETE request to delete a team by their name. Captains may delete their own
+ * team; roles with `ManageTeams` may delete any team. The team's players go
+ * back into the pool and its members' `team_id` is cleared. A team taking part
+ * in a running or paused draft cannot be deleted.
  */
 pub async fn delete_teams(
     Extension(pool): Extension<SqlitePool>,
-    Extension(tx): Extension<broadcast::Sender<String>>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    Extension(state): Extension<SharedDraftState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     AuthUser(claims): AuthUser,
     Path(team_id): Path<i64>
 ) -> impl IntoResponse {
     info!("Deleting the team {}", team_id);
 
-    let delete_result = sqlx::query!(
-        "DELETE FROM teams WHERE id = ? AND created_by = ?", team_id, claims.sub
-    )
-    .execute(&pool)
-    .await;
+    if !claims.role.has(Permission::ManageTeams) && !claims.role.has(Permission::CreateTeam) {
+        return (StatusCode::FORBIDDEN, "You do not have permission to do that.".to_string());
+    }
 
-    match delete_result {
-        Ok(res) => {
-            if res.rows_affected() == 0 {
-                (StatusCode::NOT_FOUND, format!("Team was not found."))
-            }
-            else {
-                send_team_update(&pool, &tx).await;
-                (StatusCode::OK, format!("Team was successfully removed."))
-            }
-        }
-        Err(e) => {
-            error!("Failed to delete team: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete team: {}", e))
+    match delete_team_internal(&pool, &tx, &state, team_id, &claims).await {
+        Ok(before) => {
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "delete_team",
+                target: Some(&team_id.to_string()),
+                before: Some(before),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, "Team was successfully removed.".to_string())
         }
+        Err(e) => e,
     }
-    
+}
+
+/// Deletes a team the caller may delete, returning what it was for the audit log.
+async fn delete_team_internal(
+    pool: &SqlitePool,
+    tx: &broadcast::Sender<ServerEvent>,
+    state: &SharedDraftState,
+    team_id: i64,
+    claims: &Claims,
+) -> Result<String, (StatusCode, String)> {
+    let database_error = |e: sqlx::Error| {
+        error!("Failed to delete team: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string())
+    };
+
+    /* Hold the draft still while the team leaves it */
+    let mut state_guard = state.write().await;
+
+    let team = sqlx::query_as::<_, Team>("SELECT * FROM teams WHERE id = ?")
+        .bind(team_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(database_error)?
+        .filter(|team| claims.role.has(Permission::ManageTeams) || team.created_by.as_deref() == Some(claims.sub.as_str()))
+        .ok_or((StatusCode::NOT_FOUND, "Team was not found.".to_string()))?;
+
+    let in_draft = state_guard.teams.0.iter().any(|t| t.id == team_id);
+    if in_draft && (state_guard.phase == "Drafting" || state_guard.phase == "Paused") {
+        return Err((StatusCode::CONFLICT, format!("{} is in the current draft and cannot be deleted.", team.name)));
+    }
+
+    let selections: Vec<Player> = serde_json::from_str(team.selections.as_deref().unwrap_or("[]")).unwrap_or_default();
+
+    let mut db = pool.begin().await.map_err(database_error)?;
+
+    for player in &selections {
+        sqlx::query("UPDATE players SET drafted = 0 WHERE ign = ?")
+            .bind(&player.ign)
+            .execute(&mut *db)
+            .await
+            .map_err(database_error)?;
+    }
+
+    sqlx::query("UPDATE users SET team_id = NULL WHERE team_id = ?")
+        .bind(team_id)
+        .execute(&mut *db)
+        .await
+        .map_err(database_error)?;
+
+    sqlx::query("DELETE FROM teams WHERE id = ?")
+        .bind(team_id)
+        .execute(&mut *db)
+        .await
+        .map_err(database_error)?;
+
+    let mut updated = state_guard.clone();
+    if in_draft {
+        updated.teams.0.retain(|t| t.id != team_id);
+        save_draft_state(&mut *db, &updated).await?;
+    }
+
+    db.commit().await.map_err(database_error)?;
+
+    if in_draft {
+        *state_guard = updated;
+    }
+    drop(state_guard);
+
+    send_team_update(pool, tx).await;
+    if !selections.is_empty() {
+        send_player_update(pool, tx).await;
+    }
+    if in_draft {
+        send_draft_update(tx, state).await;
+    }
+
+    Ok(format!("{} owned by {}", team.name, team.created_by.unwrap_or_default()))
 }
\ No newline at end of file
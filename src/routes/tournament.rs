@@ -0,0 +1,275 @@
+use axum::{
+    extract::{ConnectInfo, Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use tracing::{info, error};
+
+use crate::dto::tournament_dto::{ColumnMapping, GameProfile, PlayerSourceConfig, PLAYER_SOURCE_KINDS};
+use crate::services::audit::{record, AuditEntry};
+use crate::services::game_profiles::{load_game_profile, presets};
+use crate::services::player_sources::{load_column_mapping, load_column_mappings, load_source_config};
+use crate::services::rbac::{require, Authorized};
+use crate::services::validation::Valid;
+
+/* Longest file accepted for the CSV and JSON player sources */
+const MAX_UPLOAD_BYTES: usize = 1024 * 1024;
+
+/**
+ * GET where the tournament's signups are imported from.
+ */
+pub async fn get_player_source(
+    _: Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (config, upload) = load_source_config(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({
+        "source": config,
+        "upload_bytes": upload.map(|u| u.len())
+    }))))
+}
+
+/**
+ * PUT to change where signups are imported from, e.g.
+ * `{"kind": "google_sheets", "spreadsheet_id": "...", "range": "Form Responses 1"}` or `{"kind": "csv"}`.
+ */
+pub async fn set_player_source(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<PlayerSourceConfig>,
+) -> impl IntoResponse {
+    let before = load_source_config(&pool)
+        .await
+        .ok()
+        .and_then(|(config, _)| serde_json::to_string(&config).ok());
+
+    let config = match serde_json::to_string(&payload) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize player source: {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string());
+        }
+    };
+
+    match sqlx::query("UPDATE tournament SET player_source = ? WHERE id = 1")
+        .bind(&config)
+        .execute(&pool)
+        .await
+    {
+        Ok(_) => {
+            info!("{} changed the player source to {:?}.", claims.sub, payload);
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "set_player_source",
+                before,
+                after: Some(config),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, "Updated the player source.".to_string())
+        }
+        Err(e) => {
+            error!("Failed to update player source: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the player source.".to_string())
+        }
+    }
+}
+
+/**
+ * PUT the raw contents of a CSV or JSON signup file, read by the `csv` and
+ * `json` player sources. Replaces any earlier upload.
+ */
+pub async fn upload_player_source(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    body: String,
+) -> impl IntoResponse {
+    if body.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "The uploaded file is empty.".to_string());
+    }
+
+    if body.len() > MAX_UPLOAD_BYTES {
+        return (StatusCode::PAYLOAD_TOO_LARGE, format!("Uploads are limited to {} bytes.", MAX_UPLOAD_BYTES));
+    }
+
+    let before = load_source_config(&pool)
+        .await
+        .ok()
+        .and_then(|(_, upload)| upload)
+        .map(|upload| format!("{} bytes", upload.len()));
+
+    match sqlx::query("UPDATE tournament SET source_upload = ? WHERE id = 1")
+        .bind(&body)
+        .execute(&pool)
+        .await
+    {
+        Ok(_) => {
+            info!("{} uploaded a {} byte signup file.", claims.sub, body.len());
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "upload_player_source",
+                before,
+                after: Some(format!("{} bytes", body.len())),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, "Uploaded the signup file.".to_string())
+        }
+        Err(e) => {
+            error!("Failed to store upload: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not store the upload.".to_string())
+        }
+    }
+}
+
+fn check_source_kind(kind: &str) -> Result<(), (StatusCode, String)> {
+    if PLAYER_SOURCE_KINDS.contains(&kind) {
+        Ok(())
+    } else {
+        Err((StatusCode::NOT_FOUND, format!("Unknown player source {}.", kind)))
+    }
+}
+
+/**
+ * GET which signup columns are read into which player fields for a kind of
+ * player source, e.g. `/tournament/column_mappings/csv`.
+ */
+pub async fn get_column_mapping(
+    _: Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    Path(kind): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    check_source_kind(&kind)?;
+
+    let mapping = load_column_mapping(&pool, &kind)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((StatusCode::OK, Json(mapping)))
+}
+
+/**
+ * PUT the column mapping for a kind of player source. `name`, `ign` and
+ * `current_rank` must be mapped; columns are given by header text or by
+ * position, e.g. `{"name": "Name", "ign": 1, "current_rank": "Rank"}`.
+ */
+pub async fn set_column_mapping(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(kind): Path<String>,
+    Valid(payload): Valid<ColumnMapping>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    check_source_kind(&kind)?;
+
+    let mut mappings = load_column_mappings(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let after = serde_json::to_string(&payload).ok();
+    let before = mappings
+        .insert(kind.clone(), payload)
+        .and_then(|previous| serde_json::to_string(&previous).ok());
+
+    let json = serde_json::to_string(&mappings).map_err(|e| {
+        error!("Failed to serialize column mappings: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string())
+    })?;
+
+    sqlx::query("UPDATE tournament SET column_mappings = ? WHERE id = 1")
+        .bind(json)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to update column mappings: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the column mapping.".to_string())
+        })?;
+
+    info!("{} changed the column mapping of the {} player source.", claims.sub, kind);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "set_column_mapping",
+        target: Some(&kind),
+        before,
+        after,
+        ip: Some(addr.ip()),
+    }).await;
+    Ok((StatusCode::OK, "Updated the column mapping.".to_string()))
+}
+
+/**
+ * GET the tournament's game profile: its rank ladder, from lowest to highest,
+ * and the roles players sign up for.
+ */
+pub async fn get_game_profile(
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let profile = load_game_profile(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((StatusCode::OK, Json(profile)))
+}
+
+/**
+ * GET the built-in game profiles, which can be PUT as they are or edited first.
+ */
+pub async fn get_game_profile_presets(
+    _: Authorized<require::ImportPlayers>,
+) -> impl IntoResponse {
+    (StatusCode::OK, Json(presets()))
+}
+
+/**
+ * PUT the tournament's game profile. Players keep their ranks and ratings
+ * until the next import reads them with the new profile.
+ */
+pub async fn set_game_profile(
+    Authorized(claims, _): Authorized<require::ImportPlayers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Valid(payload): Valid<GameProfile>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let before = load_game_profile(&pool).await.ok().map(|profile| profile_summary(&profile));
+
+    let json = serde_json::to_string(&payload).map_err(|e| {
+        error!("Failed to serialize game profile: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string())
+    })?;
+
+    sqlx::query("UPDATE tournament SET game_profile = ? WHERE id = 1")
+        .bind(json)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to update game profile: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the game profile.".to_string())
+        })?;
+
+    info!("{} set the game profile to {}.", claims.sub, payload.game);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "set_game_profile",
+        before,
+        after: Some(profile_summary(&payload)),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
+    Ok((StatusCode::OK, "Updated the game profile.".to_string()))
+}
+
+/// e.g. "Valorant: 9 ranks, 3 divisions, 5 roles"
+fn profile_summary(profile: &GameProfile) -> String {
+    format!(
+        "{}: {} ranks, {} divisions, {} roles",
+        profile.game, profile.ranks.len(), profile.divisions, profile.roles.len()
+    )
+}
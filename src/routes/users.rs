@@ -1,23 +1,36 @@
 use axum::{
-    extract::{Extension},
+    extract::{ConnectInfo, Extension, Path, Query},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use sqlx::{SqlitePool};
 use serde_json::json;
-use tracing::{error};
-use jsonwebtoken::{encode, EncodingKey, Header};
-use chrono::{Utc};
+use chrono::Utc;
+use tracing::{info, error, warn};
+use std::net::SocketAddr;
+use tokio::sync::broadcast;
 
-use crate::dto::user_dto::{User, CreateUser, LoginUser};
-use crate::dto::claims_dto::Claims;
+use crate::dto::user_dto::{User, CreateUser, LoginUser, UpdateRole, RefreshToken, LogoutUser, ChangePassword, UserSummary, UserSearchQuery, RemoveUserQuery, SetDisabled, ResetPassword};
+use crate::dto::{draft_dto::SharedDraftState, player_dto::Player, team_dto::Team, ws_dto::ServerEvent};
 
 use crate::services::auth_user::AuthUser;
+use crate::services::channels::{ChannelRegistry, SharedChannelRegistry};
+use crate::services::jwt_keys::SharedJwtKeys;
+use crate::services::rbac::{require, Authorized, Permission, Role};
+use crate::services::sessions::{issue_session, rotate_refresh_token, revoke_access_token, revoke_refresh_token, invalidate_all_sessions};
+use crate::services::password::{hash_password, is_legacy, verify_dummy, verify_password};
+use crate::services::invites::redeem_invite;
+use crate::services::login_throttle::{account_key, clear, ip_key, locked_for, record_failure, unlock};
+use crate::services::password_resets::{create_reset_code, redeem_reset_code};
+use crate::services::websocket::{send_draft_update, send_player_update, send_team_update};
+use crate::services::audit::{record, AuditEntry};
+use crate::services::validation::Valid;
+use crate::routes::draft::save_draft_state;
 
 pub async fn create_user(
     Extension(pool): Extension<SqlitePool>,
-    Json(payload): Json<CreateUser>
+    Valid(payload): Valid<CreateUser>
 ) -> impl IntoResponse {
     /* First check if the user with that user name already exists */
     let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
@@ -27,111 +40,821 @@ pub async fn create_user(
 
     match user_result {
         Ok(result) => {
-            if result.len() > 0 {
-                return (StatusCode::CONFLICT, format!("That username already exists"));
+            if !result.is_empty() {
+                (StatusCode::CONFLICT, "That username already exists".to_string())
             }
             else {
+                let password_hash = match hash_password(&payload.password) {
+                    Ok(hash) => hash,
+                    Err(e) => {
+                        error!("Failed to hash password: {:?}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "Could not create user.".to_string());
+                    }
+                };
+
+                let mut tx = match pool.begin().await {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        error!("There was an error with the database {:?}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+                    }
+                };
+
+                /* Registration needs an unused invite; signup invites also fix the IGN */
+                let invite = match redeem_invite(&mut tx, &payload.invite_code, &payload.username).await {
+                    Ok(Some(invite)) => invite,
+                    Ok(None) => {
+                        return (StatusCode::FORBIDDEN, "Invalid or already used invite code.".to_string());
+                    }
+                    Err(e) => {
+                        error!("Failed to redeem invite: {:?}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+                    }
+                };
+
+                let (ign, ign_verified) = match (invite.player_ign, payload.ign) {
+                    (Some(player_ign), _) => (player_ign, true),
+                    (None, Some(ign)) => (ign, false),
+                    (None, None) => {
+                        return (StatusCode::BAD_REQUEST, "An IGN is required for this invite.".to_string());
+                    }
+                };
+
+                /* Tokens minted for an earlier account with this username must not carry over */
+                let sessions_valid_after = Utc::now().timestamp_millis();
+
                 /* Now insert user inside database */
-                let create_result = sqlx::query!(
+                let create_result = sqlx::query(
                     r#"
-                    INSERT INTO users (team_id, name, username, ign, password)
-                    VALUES (?, ?, ?, ?, ?)
-                    "#,
-                    None::<i64>,
-                    payload.name,
-                    payload.username,
-                    payload.ign,
-                    payload.password
+                    INSERT INTO users (team_id, name, username, ign, password, role, ign_verified, sessions_valid_after, player_ign)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, (SELECT ign FROM players WHERE ign = ? AND ?))
+                    "#
                 )
-                .execute(&pool)
+                .bind(None::<i64>)
+                .bind(&payload.name)
+                .bind(&payload.username)
+                .bind(&ign)
+                .bind(password_hash)
+                .bind(invite.role)
+                .bind(ign_verified)
+                .bind(sessions_valid_after)
+                .bind(&ign)
+                .bind(ign_verified)
+                .execute(&mut *tx)
                 .await;
 
-                match create_result {
-                    Ok(result) => {
-                        return (StatusCode::OK, format!("Successfully created user \"{}\"", payload.username));
+                if let Err(e) = create_result {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not create user in database: {}", e));
+                }
+
+                match tx.commit().await {
+                    Ok(_) => {
+                        (StatusCode::OK, format!("Successfully created user \"{}\"", payload.username))
                     }
                     Err(e) => {
-                        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not create user in database: {}", e));
+                        (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not create user in database: {}", e))
                     }
                 }
             }
         }
         Err(e) => {
             error!("There was an error with the database {:?}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("There was a database issue."))
+            (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string())
         }
     }
 }
 
+/* DELETE your own account. See `delete_user_internal` for what happens to your team. */
 pub async fn remove_user(
     AuthUser(claims): AuthUser,
     Extension(pool): Extension<SqlitePool>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    Extension(state): Extension<SharedDraftState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(query): Query<RemoveUserQuery>,
+) -> impl IntoResponse {
+    match delete_user_internal(&pool, &tx, &channels, &state, &claims.sub, query.transfer_to.as_deref()).await {
+        Ok(message) => {
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "delete_user",
+                target: Some(&claims.sub),
+                after: Some(message.clone()),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, message)
+        }
+        Err(e) => e,
+    }
+}
+
+/* DELETE another user's account */
+#[allow(clippy::too_many_arguments)]
+pub async fn delete_user(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    Extension(state): Extension<SharedDraftState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(username): Path<String>,
+    Query(query): Query<RemoveUserQuery>,
 ) -> impl IntoResponse {
-    let username = claims.sub;
+    match delete_user_internal(&pool, &tx, &channels, &state, &username, query.transfer_to.as_deref()).await {
+        Ok(message) => {
+            info!("{} deleted the account {}.", claims.sub, username);
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "delete_user",
+                target: Some(&username),
+                after: Some(message.clone()),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, message)
+        }
+        Err(e) => e,
+    }
+}
+
+/**
+ * Removes a user along with everything that points at them.
+ *
+ * With `transfer_to`, the user's team (row, draft state entry and the new
+ * owner's `team_id`) passes to that user, who becomes a captain if they were
+ * not one. Without it, the team is deleted, its players go back into the pool
+ * and members' `team_id` is cleared. A team taking part in a running or paused
+ * draft cannot be deleted, so removing its owner then requires `transfer_to`.
+ * The last enabled admin cannot be removed.
+ */
+pub async fn delete_user_internal(
+    pool: &SqlitePool,
+    tx: &broadcast::Sender<ServerEvent>,
+    channels: &ChannelRegistry,
+    state: &SharedDraftState,
+    username: &str,
+    transfer_to: Option<&str>,
+) -> Result<String, (StatusCode, String)> {
+    let database_error = |e: sqlx::Error| {
+        error!("There was an error with the database {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string())
+    };
+
+    /* Hold the draft still while its teams change hands */
+    let mut state_guard = state.write().await;
+
+    let user_id = sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+        .map_err(database_error)?
+        .ok_or((StatusCode::NOT_FOUND, format!("User {} was not found.", username)))?;
 
-    let remove_result = sqlx::query!(
+    let owned_teams = sqlx::query_as::<_, Team>("SELECT * FROM teams WHERE created_by = ?")
+        .bind(username)
+        .fetch_all(pool)
+        .await
+        .map_err(database_error)?;
+
+    let in_draft = state_guard.teams.0.iter().any(|team| team.created_by.as_deref() == Some(username));
+    let mid_draft = in_draft && (state_guard.phase == "Drafting" || state_guard.phase == "Paused");
+
+    let mut db = pool.begin().await.map_err(database_error)?;
+    let mut updated = state_guard.clone();
+    let mut players_freed = false;
+
+    match transfer_to {
+        Some(new_owner) if !owned_teams.is_empty() => {
+            let target = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+                .bind(new_owner)
+                .fetch_optional(&mut *db)
+                .await
+                .map_err(database_error)?
+                .filter(|target| target.id != user_id && !target.disabled)
+                .ok_or((StatusCode::BAD_REQUEST, format!("Cannot transfer the team to {}.", new_owner)))?;
+
+            let already_owns = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM teams WHERE created_by = ?")
+                .bind(new_owner)
+                .fetch_one(&mut *db)
+                .await
+                .map_err(database_error)?;
+
+            if already_owns > 0 {
+                return Err((StatusCode::CONFLICT, format!("{} already owns a team.", new_owner)));
+            }
+
+            sqlx::query("UPDATE teams SET created_by = ? WHERE created_by = ?")
+                .bind(new_owner)
+                .bind(username)
+                .execute(&mut *db)
+                .await
+                .map_err(database_error)?;
+
+            let role = if target.role.has(Permission::DraftPick) { target.role } else { Role::Captain };
+            sqlx::query("UPDATE users SET team_id = ?, role = ? WHERE id = ?")
+                .bind(owned_teams[0].id)
+                .bind(role)
+                .bind(target.id)
+                .execute(&mut *db)
+                .await
+                .map_err(database_error)?;
+
+            for team in updated.teams.0.iter_mut() {
+                if team.created_by.as_deref() == Some(username) {
+                    team.created_by = Some(new_owner.to_string());
+                }
+            }
+        }
+        _ if mid_draft => {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("{} owns a team in the current draft. Transfer it with ?transfer_to= first.", username),
+            ));
+        }
+        _ => {
+            for team in &owned_teams {
+                let selections: Vec<Player> = serde_json::from_str(
+                    team.selections.as_deref().unwrap_or("[]")
+                ).unwrap_or_default();
+
+                for player in selections {
+                    sqlx::query("UPDATE players SET drafted = 0 WHERE ign = ?")
+                        .bind(&player.ign)
+                        .execute(&mut *db)
+                        .await
+                        .map_err(database_error)?;
+                    players_freed = true;
+                }
+
+                sqlx::query("UPDATE users SET team_id = NULL WHERE team_id = ?")
+                    .bind(team.id)
+                    .execute(&mut *db)
+                    .await
+                    .map_err(database_error)?;
+            }
+
+            sqlx::query("DELETE FROM teams WHERE created_by = ?")
+                .bind(username)
+                .execute(&mut *db)
+                .await
+                .map_err(database_error)?;
+
+            updated.teams.0.retain(|team| team.created_by.as_deref() != Some(username));
+        }
+    }
+
+    sqlx::query("DELETE FROM player_claims WHERE username = ? AND status = 'pending'")
+        .bind(username)
+        .execute(&mut *db)
+        .await
+        .map_err(database_error)?;
+
+    /* Dropping the transaction undoes the team changes above */
+    let deleted = sqlx::query(
         r#"
-        DELETE FROM users WHERE username = ?
-        "#,
-        username
+        DELETE FROM users
+        WHERE id = ?1
+          AND (role != 'admin' OR disabled = 1
+               OR (SELECT COUNT(*) FROM users WHERE role = 'admin' AND disabled = 0 AND id != ?1) > 0)
+        "#
     )
+    .bind(user_id)
+    .execute(&mut *db)
+    .await
+    .map_err(database_error)?;
+
+    if deleted.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, "The last admin cannot be deleted.".to_string()));
+    }
+
+    if in_draft {
+        save_draft_state(&mut *db, &updated).await?;
+    }
+
+    db.commit().await.map_err(database_error)?;
+
+    if in_draft {
+        *state_guard = updated;
+    }
+
+    drop(state_guard);
+
+    channels.disconnect_user(username);
+
+    if !owned_teams.is_empty() {
+        send_team_update(pool, tx).await;
+    }
+    if players_freed {
+        send_player_update(pool, tx).await;
+    }
+    if in_draft {
+        send_draft_update(tx, state).await;
+    }
+
+    Ok(match (transfer_to, owned_teams.is_empty()) {
+        (Some(new_owner), false) => format!("Successfully removed {}; their team now belongs to {}.", username, new_owner),
+        (None, false) => format!("Successfully removed {} and their team.", username),
+        _ => format!("Successfully removed {}", username),
+    })
+}
+
+/**
+ * GET users for admins, with the team each one owns. `search` matches the
+ * username, name or IGN; `role` filters by role; `limit` and `offset` page.
+ */
+pub async fn get_users(
+    _: Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<UserSearchQuery>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let pattern = query.search.as_ref().map(|search| format!("%{}%", search.trim()));
+
+    let users = sqlx::query_as::<_, UserSummary>(
+        r#"
+        SELECT
+            users.id, users.name, users.username, users.ign, users.role, users.ign_verified,
+            users.player_ign, users.team_id, users.disabled,
+            teams.id AS owned_team_id, teams.name AS owned_team_name
+        FROM users
+        LEFT JOIN teams ON teams.created_by = users.username
+        WHERE (?1 IS NULL OR users.username LIKE ?1 OR users.name LIKE ?1 OR users.ign LIKE ?1)
+          AND (?2 IS NULL OR users.role = ?2)
+        ORDER BY users.username
+        LIMIT ?3 OFFSET ?4
+        "#
+    )
+    .bind(pattern)
+    .bind(query.role)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch users: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch users")
+    })?;
+
+    Ok((StatusCode::OK, Json(users)))
+}
+
+/* PUT to disable or re-enable an account. Disabling ends every session. */
+pub async fn set_user_disabled(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(username): Path<String>,
+    Json(payload): Json<SetDisabled>,
+) -> impl IntoResponse {
+    if payload.disabled && username == claims.sub {
+        return (StatusCode::BAD_REQUEST, "You cannot disable your own account.".to_string());
+    }
+
+    let user_id = match sqlx::query_scalar::<_, i64>("UPDATE users SET disabled = ? WHERE username = ? RETURNING id")
+        .bind(payload.disabled)
+        .bind(&username)
+        .fetch_optional(&pool)
+        .await {
+            Ok(Some(id)) => id,
+            Ok(None) => return (StatusCode::NOT_FOUND, format!("User {} was not found.", username)),
+            Err(e) => {
+                error!("Failed to update account: {:?}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the account.".to_string());
+            }
+        };
+
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: if payload.disabled { "disable_user" } else { "enable_user" },
+        target: Some(&username),
+        ip: Some(addr.ip()),
+        ..Default::default()
+    }).await;
+
+    if payload.disabled {
+        if let Err(e) = invalidate_all_sessions(&pool, &channels, user_id).await {
+            return e;
+        }
+
+        info!("{} disabled the account {}.", claims.sub, username);
+        (StatusCode::OK, format!("Disabled {}.", username))
+    } else {
+        info!("{} enabled the account {}.", claims.sub, username);
+        (StatusCode::OK, format!("Enabled {}.", username))
+    }
+}
+
+/* POST to issue a one-time password reset code for a user, to be passed on to them */
+pub async fn issue_password_reset(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(username): Path<String>,
+) -> impl IntoResponse {
+    let user_id = match sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username = ?")
+        .bind(&username)
+        .fetch_optional(&pool)
+        .await {
+            Ok(Some(id)) => id,
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, Json(json!({"error": format!("User {} was not found.", username)})));
+            }
+            Err(e) => {
+                error!("There was an error with the database {:?}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "There was a database issue."})));
+            }
+        };
+
+    match create_reset_code(&pool, user_id, &claims.sub).await {
+        Ok(code) => {
+            info!("{} issued a password reset code for {}.", claims.sub, username);
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "issue_password_reset",
+                target: Some(&username),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, Json(json!({"username": username, "code": code})))
+        }
+        Err(e) => {
+            error!("Failed to create reset code: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Could not create a reset code."})))
+        }
+    }
+}
+
+/* POST to set a new password with a reset code. Ends every existing session. */
+pub async fn reset_password(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Valid(payload): Valid<ResetPassword>,
+) -> impl IntoResponse {
+    match locked_for(&pool, &[&account_key(&payload.username), &ip_key(addr.ip())]).await {
+        Ok(Some(_)) => {
+            return (StatusCode::TOO_MANY_REQUESTS, "Too many failed attempts. Try again later.".to_string());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("There was an error with the database {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+        }
+    }
+
+    let password_hash = match hash_password(&payload.new_password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            error!("Failed to hash password: {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Could not reset password.".to_string());
+        }
+    };
+
+    let user_id = match redeem_reset_code(&pool, &payload.username, &payload.code).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            if let Err(e) = record_failure(&pool, &payload.username, addr.ip()).await {
+                error!("Failed to record reset failure: {:?}", e);
+            }
+            return (StatusCode::UNAUTHORIZED, "Invalid or expired reset code.".to_string());
+        }
+        Err(e) => {
+            error!("There was an error with the database {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+        }
+    };
+
+    if let Err(e) = sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+    {
+        error!("Failed to update password: {:?}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Could not reset password.".to_string());
+    }
+
+    if let Err(e) = invalidate_all_sessions(&pool, &channels, user_id).await {
+        return e;
+    }
+
+    info!("{} reset their password with a reset code.", payload.username);
+    (StatusCode::OK, "Password reset. Please log in.".to_string())
+}
+
+/**
+ * PUT to change another user's role. Their sessions are ended so the new role
+ * applies from their next login. You can't change your own role, and the last
+ * enabled admin can't be demoted.
+ */
+pub async fn update_user_role(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(username): Path<String>,
+    Valid(payload): Valid<UpdateRole>,
+) -> impl IntoResponse {
+    if username == claims.sub {
+        return (StatusCode::FORBIDDEN, "You cannot change your own role.".to_string());
+    }
+
+    let (user_id, previous) = match sqlx::query_as::<_, (i64, Role)>("SELECT id, role FROM users WHERE username = ?")
+        .bind(&username)
+        .fetch_optional(&pool)
+        .await {
+            Ok(Some(user)) => user,
+            Ok(None) => return (StatusCode::NOT_FOUND, format!("User {} was not found.", username)),
+            Err(e) => {
+                error!("Failed to look up role: {:?}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the user's role.".to_string());
+            }
+        };
+
+    // Checked in the same statement as the update so two demotions can't both pass
+    let update_result = sqlx::query(
+        r#"
+        UPDATE users SET role = ?1
+        WHERE id = ?2
+          AND (role != 'admin' OR ?1 = 'admin'
+               OR (SELECT COUNT(*) FROM users WHERE role = 'admin' AND disabled = 0 AND id != ?2) > 0)
+        "#
+    )
+    .bind(payload.role)
+    .bind(user_id)
     .execute(&pool)
     .await;
 
-    match remove_result {
-        Ok(result) => {
-            return (StatusCode::OK, format!("Successfully removed {}", username));
+    match update_result {
+        Ok(result) if result.rows_affected() == 0 => {
+            return (StatusCode::CONFLICT, "The last admin cannot be demoted.".to_string());
         }
+        Ok(_) => {}
         Err(e) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not remove {} for this reason {}.", username, e));
+            error!("Failed to update role: {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Could not update the user's role.".to_string());
         }
     }
+
+    if previous != payload.role
+        && let Err(e) = invalidate_all_sessions(&pool, &channels, user_id).await
+    {
+        return e;
+    }
+
+    info!("{} changed the role of {} to {:?}.", claims.sub, username, payload.role);
+    record(&pool, AuditEntry {
+        actor: &claims.sub,
+        action: "update_role",
+        target: Some(&username),
+        before: Some(format!("{:?}", previous)),
+        after: Some(format!("{:?}", payload.role)),
+        ip: Some(addr.ip()),
+    }).await;
+    (StatusCode::OK, format!("Updated the role of {}.", username))
 }
 
 /* POST to login the user */
 pub async fn login_user(
     Extension(pool): Extension<SqlitePool>,
-    Json(payload): Json<LoginUser>,
+    Extension(keys): Extension<SharedJwtKeys>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Valid(payload): Valid<LoginUser>,
 ) -> impl IntoResponse {
+    let account = account_key(&payload.username);
+    let address = ip_key(addr.ip());
+
+    match locked_for(&pool, &[&account, &address]).await {
+        Ok(Some(retry_after)) => {
+            warn!("Rejected login for {} from {}: locked out.", payload.username, addr.ip());
+            return (StatusCode::TOO_MANY_REQUESTS, Json(json!({
+                "error": "Too many failed login attempts. Try again later.",
+                "retry_after": retry_after
+            })));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("There was an error with the database {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "There was a database issue."})));
+        }
+    }
+
     let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
         .bind(&payload.username)
-        .fetch_all(&pool)
+        .fetch_optional(&pool)
         .await;
 
-    match user_result {
-        Ok(result) => {
-            if result.len() > 0 {
-                /* Should only be one account */
-                let user = &result[0];
-                if payload.password == user.password {
-                    let claims = Claims {
-                        sub: user.username.clone(),
-                        exp: (Utc::now() + chrono::Duration::hours(24)).timestamp() as usize
-                    };
-
-                    let token = encode(
-                        &Header::default(),
-                        &claims,
-                        &EncodingKey::from_secret("sunnycup".as_ref())
-                    ).expect("Token encoding failed");
-
-                    return (StatusCode::OK, Json(json!({
-                        "name": user.name,
-                        "token": token
-                    })));
-                }
-                else {
-                    return (StatusCode::UNAUTHORIZED, Json(json!({"error": "Incorrect username or password."})));
+    let user = match user_result {
+        Ok(user) => user,
+        Err(e) => {
+            error!("There was an error with the database {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "There was a database issue."})));
+        }
+    };
+
+    /* Unknown usernames and wrong passwords get the same response, in the same time */
+    let verified = match &user {
+        Some(user) => verify_password(&payload.password, &user.password),
+        None => verify_dummy(&payload.password),
+    };
+
+    let Some(user) = user.filter(|_| verified) else {
+        if let Err(e) = record_failure(&pool, &payload.username, addr.ip()).await {
+            error!("Failed to record login failure: {:?}", e);
+        }
+        return (StatusCode::UNAUTHORIZED, Json(json!({"error": "Incorrect username or password."})));
+    };
+
+    if let Err(e) = clear(&pool, &account).await {
+        error!("Failed to clear login failures: {:?}", e);
+    }
+
+    if is_legacy(&user.password) {
+        upgrade_legacy_password(&pool, user.id, &payload.password).await;
+    }
+
+    match issue_session(&pool, &keys, &user).await {
+        Ok(session) => (StatusCode::OK, Json(json!({
+            "name": user.name,
+            "token": session.token,
+            "refresh_token": session.refresh_token,
+            "expires_in": session.expires_in
+        }))),
+        Err((status, e)) => (status, Json(json!({"error": e}))),
+    }
+}
+
+/* POST to lift a login lockout on an account and the address it was locked from */
+pub async fn unlock_user(
+    Authorized(claims, _): Authorized<require::ManageUsers>,
+    Extension(pool): Extension<SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(username): Path<String>,
+) -> impl IntoResponse {
+    match unlock(&pool, &username).await {
+        Ok(true) => {
+            info!("{} unlocked the account {}.", claims.sub, username);
+            record(&pool, AuditEntry {
+                actor: &claims.sub,
+                action: "unlock_user",
+                target: Some(&username),
+                ip: Some(addr.ip()),
+                ..Default::default()
+            }).await;
+            (StatusCode::OK, format!("Unlocked {}.", username))
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, format!("{} is not locked out.", username)),
+        Err(e) => {
+            error!("Failed to unlock account: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not unlock the account.".to_string())
+        }
+    }
+}
+
+/* POST to exchange a refresh token for a new access token and refresh token */
+pub async fn refresh_token(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    Extension(keys): Extension<SharedJwtKeys>,
+    Json(payload): Json<RefreshToken>,
+) -> impl IntoResponse {
+    match rotate_refresh_token(&pool, &channels, &keys, &payload.refresh_token).await {
+        Ok(session) => (StatusCode::OK, Json(json!(session))),
+        Err((status, e)) => (status, Json(json!({"error": e}))),
+    }
+}
+
+/* POST to log out. Revokes the current access token and, if given, its refresh token; `all` ends every session. */
+pub async fn logout_user(
+    AuthUser(claims): AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    payload: Option<Json<LogoutUser>>,
+) -> impl IntoResponse {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    if let Err(e) = revoke_access_token(&pool, &claims).await {
+        return e;
+    }
+    channels.disconnect_token(&claims.jti);
+
+    if let Some(refresh_token) = &payload.refresh_token
+        && let Err(e) = revoke_refresh_token(&pool, &claims.sub, refresh_token).await
+    {
+        return e;
+    }
+
+    if payload.all {
+        let user_id = match sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username = ?")
+            .bind(&claims.sub)
+            .fetch_one(&pool)
+            .await {
+                Ok(id) => id,
+                Err(e) => {
+                    error!("There was an error with the database {:?}", e);
+                    return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
                 }
-            }
-            else {
-                return (StatusCode::NOT_FOUND, Json(json!({"error": "User was not found."})));
-            }
+            };
+
+        if let Err(e) = invalidate_all_sessions(&pool, &channels, user_id).await {
+            return e;
         }
+    }
+
+    (StatusCode::OK, format!("Logged out {}.", claims.sub))
+}
+
+/* PUT to change your own password. Ends every existing session. */
+pub async fn change_password(
+    AuthUser(claims): AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Valid(payload): Valid<ChangePassword>,
+) -> impl IntoResponse {
+    /* A stolen access token must not become a way around the login lockout */
+    let account = account_key(&claims.sub);
+    match locked_for(&pool, &[&account, &ip_key(addr.ip())]).await {
+        Ok(Some(_)) => {
+            return (StatusCode::TOO_MANY_REQUESTS, "Too many failed attempts. Try again later.".to_string());
+        }
+        Ok(None) => {}
         Err(e) => {
             error!("There was an error with the database {:?}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "There was a database issue."})))
+            return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+        }
+    }
+
+    let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_optional(&pool)
+        .await {
+            Ok(Some(user)) => user,
+            Ok(None) => return (StatusCode::NOT_FOUND, "User was not found.".to_string()),
+            Err(e) => {
+                error!("There was an error with the database {:?}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string());
+            }
+        };
+
+    if !verify_password(&payload.current_password, &user.password) {
+        if let Err(e) = record_failure(&pool, &claims.sub, addr.ip()).await {
+            error!("Failed to record password failure: {:?}", e);
+        }
+        return (StatusCode::UNAUTHORIZED, "Incorrect password.".to_string());
+    }
+
+    if let Err(e) = clear(&pool, &account).await {
+        error!("Failed to clear login failures: {:?}", e);
+    }
+
+    let password_hash = match hash_password(&payload.new_password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            error!("Failed to hash password: {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Could not change password.".to_string());
         }
+    };
+
+    if let Err(e) = sqlx::query!("UPDATE users SET password = ? WHERE id = ?", password_hash, user.id)
+        .execute(&pool)
+        .await
+    {
+        error!("Failed to update password: {:?}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Could not change password.".to_string());
+    }
+
+    if let Err(e) = invalidate_all_sessions(&pool, &channels, user.id).await {
+        return e;
     }
 
-}
\ No newline at end of file
+    (StatusCode::OK, "Password changed. Please log in again.".to_string())
+}
+
+/* Replaces a plaintext password from before hashing was introduced with its hash */
+async fn upgrade_legacy_password(pool: &SqlitePool, user_id: i64, password: &str) {
+    let password_hash = match hash_password(password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            error!("Failed to hash legacy password: {:?}", e);
+            return;
+        }
+    };
+
+    let result = sqlx::query!(
+        "UPDATE users SET password = ? WHERE id = ?",
+        password_hash,
+        user_id
+    )
+    .execute(pool)
+    .await;
+
+    match result {
+        Ok(_) => info!("Rehashed legacy password for user {}.", user_id),
+        Err(e) => error!("Failed to store rehashed password: {:?}", e),
+    }
+}
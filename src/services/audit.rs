@@ -0,0 +1,65 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use std::net::IpAddr;
+use tracing::error;
+
+use crate::dto::draft_dto::{DraftState, SharedDraftState};
+
+/**
+ * One audited action. `before` and `after` are short human-readable summaries
+ * of what the action changed, e.g. a role or the draft's phase and turn.
+ */
+#[derive(Default)]
+pub struct AuditEntry<'a> {
+    pub actor: &'a str,
+    pub action: &'a str,
+    pub target: Option<&'a str>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+/**
+ * Appends an entry to the audit log. Failures are logged rather than returned
+ * so they never fail the action being audited.
+ */
+pub async fn record(pool: &SqlitePool, entry: AuditEntry<'_>) {
+    let created_at = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO audit_log (actor, action, target, before, after, ip, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(entry.actor)
+    .bind(entry.action)
+    .bind(entry.target)
+    .bind(entry.before)
+    .bind(entry.after)
+    .bind(entry.ip.map(|ip| ip.to_string()))
+    .bind(created_at)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to write audit log entry for {}: {:?}", entry.action, e);
+    }
+}
+
+/// The parts of the draft state worth comparing before and after an action.
+pub fn draft_summary(state: &DraftState) -> String {
+    format!(
+        "{}, turn {}, {} teams, {} picks",
+        state.phase,
+        state.current_turn,
+        state.teams.0.len(),
+        state.pick_history.0.len()
+    )
+}
+
+/// Records a draft action that succeeded, summarising the draft as it is now.
+pub async fn record_draft_action(pool: &SqlitePool, state: &SharedDraftState, mut entry: AuditEntry<'_>) {
+    entry.after = Some(draft_summary(&*state.read().await));
+    record(pool, entry).await;
+}
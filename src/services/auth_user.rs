@@ -2,10 +2,12 @@ use axum::{
     extract::FromRequestParts,
     http::{request::Parts, StatusCode},
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use sqlx::SqlitePool;
 use tracing::error;
 
 use crate::dto::claims_dto::Claims;
+use crate::services::jwt_keys::{JwtKeys, SharedJwtKeys};
+use crate::services::sessions::ensure_session_active;
 
 pub struct AuthUser(pub Claims);
 
@@ -25,16 +27,29 @@ where
             .and_then(|s| s.strip_prefix("Bearer "))
             .ok_or((StatusCode::UNAUTHORIZED, "Missing or invalid Authorization header"))?;
 
-        let claims = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret("sunnycup".as_ref()),
-            &Validation::default(),
-        )
-        .map_err(|e| {
-            error!("Token decoding failed: {:?}", e);
-            (StatusCode::UNAUTHORIZED, "Invalid token")
-        })?;
-
-        Ok(AuthUser(claims.claims))
+        let keys = parts
+            .extensions
+            .get::<SharedJwtKeys>()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "JWT keys are not configured"))?;
+
+        let claims = decode_token(keys, token)?;
+
+        let pool = parts
+            .extensions
+            .get::<SqlitePool>()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Database is not configured"))?;
+
+        ensure_session_active(pool, &claims).await?;
+
+        Ok(AuthUser(claims))
     }
 }
+
+/// Validates a bearer token and returns its claims. Shared with the websocket
+/// handler, which receives the token as a query parameter instead of a header.
+pub fn decode_token(keys: &JwtKeys, token: &str) -> Result<Claims, (StatusCode, &'static str)> {
+    keys.verify(token).map_err(|e| {
+        error!("Token decoding failed: {:?}", e);
+        (StatusCode::UNAUTHORIZED, "Invalid token")
+    })
+}
@@ -0,0 +1,166 @@
+use axum::extract::ws::Message;
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+
+use crate::dto::claims_dto::Claims;
+use crate::services::rbac::{Permission, Role};
+
+/// A channel name as it appears in the `channel` field of targeted messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Channel {
+    User(String),
+    Team(i64),
+    Admin,
+}
+
+impl Channel {
+    /// Parses `user:<username>`, `team:<id>` or `admin`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            Some(("user", username)) if !username.is_empty() => Some(Channel::User(username.to_string())),
+            Some(("team", id)) => id.parse().ok().map(Channel::Team),
+            None if name == "admin" => Some(Channel::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Channel::User(username) => format!("user:{}", username),
+            Channel::Team(id) => format!("team:{}", id),
+            Channel::Admin => "admin".to_string(),
+        }
+    }
+}
+
+struct Subscriber {
+    username: String,
+    role: Role,
+    jti: String,
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+/**
+ * Tracks the outbound queue of every authenticated websocket so messages can be
+ * delivered to specific users instead of everyone on the broadcast channel.
+ */
+#[derive(Default)]
+pub struct ChannelRegistry {
+    subscribers: Mutex<HashMap<u64, Subscriber>>,
+    next_id: Mutex<u64>,
+}
+
+pub type SharedChannelRegistry = Arc<ChannelRegistry>;
+
+impl ChannelRegistry {
+    /**
+     * Adds a connection's outbound queue under the username, role and token id
+     * it authenticated with. The connection is removed when the returned
+     * subscription is dropped.
+     */
+    pub fn subscribe(
+        self: &Arc<Self>,
+        claims: &Claims,
+        tx: mpsc::UnboundedSender<Message>,
+    ) -> ChannelSubscription {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+
+        self.subscribers.lock().unwrap().insert(id, Subscriber {
+            username: claims.sub.clone(),
+            role: claims.role,
+            jti: claims.jti.clone(),
+            tx,
+        });
+
+        ChannelSubscription { registry: Arc::clone(self), id }
+    }
+
+    /// Sends to every connection belonging to one of `usernames`, returning how many received it.
+    pub fn send_to_users(&self, usernames: &[String], msg: &str) -> usize {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| usernames.contains(&s.username))
+            .filter(|s| s.tx.send(Message::Text(msg.into())).is_ok())
+            .count()
+    }
+
+    pub fn send_to_user(&self, username: &str, msg: &str) -> usize {
+        self.send_to_users(&[username.to_string()], msg)
+    }
+
+    /// Sends to every connection whose role grants `permission`.
+    pub fn send_to_permitted(&self, permission: Permission, msg: &str) -> usize {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| s.role.has(permission))
+            .filter(|s| s.tx.send(Message::Text(msg.into())).is_ok())
+            .count()
+    }
+
+    /**
+     * Stops delivering to a user's connections and closes them, e.g. when their
+     * sessions end or their role changes. Their role was fixed when they connected.
+     */
+    pub fn disconnect_user(&self, username: &str) -> usize {
+        self.disconnect(|s| s.username == username)
+    }
+
+    /// Closes the connections opened with one access token, when it is logged out.
+    pub fn disconnect_token(&self, jti: &str) -> usize {
+        self.disconnect(|s| s.jti == jti)
+    }
+
+    fn disconnect(&self, matches: impl Fn(&Subscriber) -> bool) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let ids: Vec<u64> = subscribers.iter().filter(|(_, s)| matches(s)).map(|(id, _)| *id).collect();
+
+        for id in &ids {
+            if let Some(subscriber) = subscribers.remove(id) {
+                let _ = subscriber.tx.send(Message::Close(None));
+            }
+        }
+
+        ids.len()
+    }
+}
+
+pub struct ChannelSubscription {
+    registry: SharedChannelRegistry,
+    id: u64,
+}
+
+impl Drop for ChannelSubscription {
+    fn drop(&mut self) {
+        self.registry.subscribers.lock().unwrap().remove(&self.id);
+    }
+}
+
+/**
+ * Usernames allowed on a team's channel: the captain who created the team and
+ * any user whose `team_id` points at it.
+ */
+pub async fn team_members(pool: &SqlitePool, team_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT created_by FROM teams WHERE id = ? AND created_by IS NOT NULL
+        UNION
+        SELECT username FROM users WHERE team_id = ?
+        "#,
+    )
+    .bind(team_id)
+    .bind(team_id)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,284 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::dto::chat_dto::{ChatDeleted, ChatMessage, ChatMessageUpdate};
+use crate::dto::ws_dto::{EventKind, ServerEvent};
+
+/// Longest chat message accepted, in characters.
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Each user may send at most `RATE_LIMIT_MESSAGES` within `RATE_LIMIT_WINDOW`.
+const RATE_LIMIT_MESSAGES: usize = 5;
+const RATE_LIMIT_WINDOW: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Longest timed mute, in minutes (30 days). Longer than that, mute indefinitely.
+const MAX_MUTE_MINUTES: i64 = 30 * 24 * 60;
+
+/**
+ * Sliding-window rate limit on chat messages, per user.
+ */
+#[derive(Default)]
+pub struct ChatLimiter {
+    recent: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+pub type SharedChatLimiter = Arc<ChatLimiter>;
+
+impl ChatLimiter {
+    /// Records a message from `username`, or returns false if they are over the limit.
+    fn allow(&self, username: &str) -> bool {
+        let mut recent = self.recent.lock().unwrap();
+        let sent = recent.entry(username.to_string()).or_default();
+        let now = Instant::now();
+
+        while sent.front().is_some_and(|t| now.duration_since(*t) > RATE_LIMIT_WINDOW) {
+            sent.pop_front();
+        }
+
+        if sent.len() >= RATE_LIMIT_MESSAGES {
+            return false;
+        }
+
+        sent.push_back(now);
+        true
+    }
+}
+
+/**
+ * Stores a chat message from a user and broadcasts it to the draft room.
+ */
+pub async fn post_chat_message(
+    pool: &SqlitePool,
+    tx: &broadcast::Sender<ServerEvent>,
+    limiter: &SharedChatLimiter,
+    username: &str,
+    body: &str,
+) -> Result<String, (StatusCode, String)> {
+    let body = body.trim();
+
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Chat messages cannot be empty.".to_string()));
+    }
+
+    if body.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Chat messages are limited to {} characters.", MAX_MESSAGE_LENGTH),
+        ));
+    }
+
+    ensure_not_muted(pool, username).await?;
+
+    if !limiter.allow(username) {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "You are sending messages too quickly.".to_string()));
+    }
+
+    insert_message(pool, tx, Some(username), body).await?;
+
+    Ok("Message sent.".to_string())
+}
+
+/**
+ * Posts an announcement from the system into the chat, e.g. each pick. Failures
+ * are logged rather than returned so they never fail the action being announced.
+ */
+pub async fn post_system_message(pool: &SqlitePool, tx: &broadcast::Sender<ServerEvent>, body: &str) {
+    if let Err((_, e)) = insert_message(pool, tx, None, body).await {
+        error!("Failed to post system chat message: {}", e);
+    }
+}
+
+async fn insert_message(
+    pool: &SqlitePool,
+    tx: &broadcast::Sender<ServerEvent>,
+    username: Option<&str>,
+    body: &str,
+) -> Result<(), (StatusCode, String)> {
+    let created_at = Utc::now().to_rfc3339();
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO chat_messages (username, body, created_at, deleted)
+        VALUES (?, ?, ?, 0)
+        "#,
+        username,
+        body,
+        created_at
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to save chat message: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save chat message".to_string())
+    })?;
+
+    let update = ChatMessageUpdate {
+        r#type: "chat_message".to_string(),
+        message: ChatMessage {
+            id: result.last_insert_rowid(),
+            username: username.map(str::to_string),
+            body: body.to_string(),
+            created_at,
+            deleted: false,
+        },
+    };
+
+    match serde_json::to_string(&update) {
+        Ok(json) => {
+            let _ = tx.send(ServerEvent { kind: EventKind::ChatMessage, data: json });
+        }
+        Err(e) => {
+            error!("Failed to serialize chat message: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Hides a message from the chat history and tells clients to remove it.
+ */
+pub async fn delete_chat_message(
+    pool: &SqlitePool,
+    tx: &broadcast::Sender<ServerEvent>,
+    message_id: i64,
+) -> Result<String, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "UPDATE chat_messages SET deleted = 1 WHERE id = ? AND deleted = 0",
+        message_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete chat message: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete chat message".to_string())
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Chat message was not found.".to_string()));
+    }
+
+    let update = ChatDeleted {
+        r#type: "chat_deleted".to_string(),
+        id: message_id,
+    };
+
+    if let Ok(json) = serde_json::to_string(&update) {
+        let _ = tx.send(ServerEvent { kind: EventKind::ChatDeleted, data: json });
+    }
+
+    info!("Deleted chat message {}.", message_id);
+    Ok(format!("Deleted chat message {}.", message_id))
+}
+
+/**
+ * Mutes a user for `minutes`, or until unmuted if no duration is given.
+ */
+pub async fn mute_user(
+    pool: &SqlitePool,
+    username: &str,
+    minutes: Option<i64>,
+    muted_by: &str,
+) -> Result<String, (StatusCode, String)> {
+    let muted_until = match minutes {
+        Some(m) if !(1..=MAX_MUTE_MINUTES).contains(&m) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("A mute must last 1 to {} minutes.", MAX_MUTE_MINUTES),
+            ));
+        }
+        Some(m) => Some(
+            Duration::try_minutes(m)
+                .and_then(|length| Utc::now().checked_add_signed(length))
+                .ok_or((StatusCode::BAD_REQUEST, "Invalid mute length.".to_string()))?
+                .to_rfc3339(),
+        ),
+        None => None,
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO chat_mutes (username, muted_until, muted_by)
+        VALUES (?, ?, ?)
+        ON CONFLICT(username) DO UPDATE SET
+            muted_until = excluded.muted_until,
+            muted_by = excluded.muted_by
+        "#,
+        username,
+        muted_until,
+        muted_by
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to mute user: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to mute user".to_string())
+    })?;
+
+    info!("{} muted {} in chat.", muted_by, username);
+    Ok(match minutes {
+        Some(m) => format!("Muted {} for {} minutes.", username, m),
+        None => format!("Muted {}.", username),
+    })
+}
+
+pub async fn unmute_user(pool: &SqlitePool, username: &str) -> Result<String, (StatusCode, String)> {
+    sqlx::query!("DELETE FROM chat_mutes WHERE username = ?", username)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to unmute user: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to unmute user".to_string())
+        })?;
+
+    Ok(format!("Unmuted {}.", username))
+}
+
+/**
+ * Refuses anything a muted user tries to say, in the draft chat or on a channel.
+ */
+pub async fn ensure_not_muted(pool: &SqlitePool, username: &str) -> Result<(), (StatusCode, String)> {
+    match muted_until(pool, username).await? {
+        Some(Some(until)) => Err((StatusCode::FORBIDDEN, format!("You are muted until {}.", until.to_rfc3339()))),
+        Some(None) => Err((StatusCode::FORBIDDEN, "You are muted.".to_string())),
+        None => Ok(()),
+    }
+}
+
+/**
+ * `Some(None)` for an indefinite mute, `Some(Some(until))` for a mute that has
+ * not yet expired, `None` if the user may chat.
+ */
+async fn muted_until(
+    pool: &SqlitePool,
+    username: &str,
+) -> Result<Option<Option<DateTime<Utc>>>, (StatusCode, String)> {
+    let mute = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT muted_until FROM chat_mutes WHERE username = ?"
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to check chat mute: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to check chat mute".to_string())
+    })?;
+
+    Ok(match mute {
+        None => None,
+        Some(None) => Some(None),
+        Some(Some(until)) => DateTime::parse_from_rfc3339(&until)
+            .ok()
+            .map(|until| until.with_timezone(&Utc))
+            .filter(|until| *until > Utc::now())
+            .map(Some),
+    })
+}
@@ -1,62 +1,352 @@
-use serde_json::{Value};
+use serde_json::Value;
 
+use crate::dto::import_dto::{FormattedSignups, RowProblem};
 use crate::dto::player_dto::{RawPlayer, PlayerCard};
+use crate::dto::tournament_dto::{ColumnMapping, ColumnRef, GameProfile};
+use crate::services::game_profiles::{normalize_roles, parse_rank, skill_rating};
 
+/// Sheets returns every cell as a string, but uploaded JSON may not.
+fn cell_text(cell: &Value) -> Option<String> {
+    match cell {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        Value::Null => Some(String::new()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn problem(row: usize, column: Option<&str>, problem: impl Into<String>, skipped: bool) -> RowProblem {
+    RowProblem { row, column: column.map(str::to_string), problem: problem.into(), skipped }
+}
 
-fn rank_to_number(rank: &str) -> u8 {
-    match rank.trim() {
-        "Iron" => 1,
-        "Bronze" => 2,
-        "Silver" => 3,
-        "Gold" => 4,
-        "Plat" | "Platinum" => 5,
-        "Diamond" => 6,
-        "Ascendant" => 7,
-        "Immortal" => 8,
-        "Radiant" => 9,
-        _ => 0,
+fn describe(column: &ColumnRef) -> String {
+    match column {
+        ColumnRef::Index(index) => format!("column {}", index),
+        ColumnRef::Header(header) => header.clone(),
     }
 }
 
-pub fn format_responses(rows: &[Value]) -> Vec<PlayerCard> {
-    let headers = rows[0].as_array().expect("Expected headers to be an array");
-    let data_rows = &rows[1..];
+/// Finds a mapped column in the header row. Header text is matched ignoring case.
+fn resolve(headers: &[String], column: &ColumnRef) -> Option<usize> {
+    match column {
+        ColumnRef::Index(index) => (*index < headers.len()).then_some(*index),
+        ColumnRef::Header(header) => headers.iter().position(|h| h.eq_ignore_ascii_case(header.trim())),
+    }
+}
 
-    let header_map: Vec<String> = headers.iter().map(|v| v.as_str().unwrap().to_string()).collect();
+/// Where each field is in this table, after checking the mapped columns exist.
+struct Columns {
+    name: usize,
+    ign: usize,
+    current_rank: usize,
+    peak_rank: Option<usize>,
+    teammate_preferences: Option<usize>,
+    roles: Option<usize>,
+    attributes: Vec<usize>,
+}
 
-    let mut players: Vec<RawPlayer> = vec![];
+/**
+ * Resolves the column mapping against the header row. A missing required
+ * column fails the whole table; a missing optional one is reported and left blank.
+ */
+fn resolve_columns(headers: &[String], mapping: &ColumnMapping, problems: &mut Vec<RowProblem>) -> Option<Columns> {
+    let mut optional = |field: &str, column: &Option<ColumnRef>| {
+        let column = column.as_ref()?;
+        let index = resolve(headers, column);
+        if index.is_none() {
+            problems.push(problem(1, Some(&describe(column)), format!("Column for {} was not found and was left blank", field), false));
+        }
+        index
+    };
 
-    for row in data_rows {
-        let row_vals: Vec<String> = row.as_array().unwrap().iter()
-            .map(|v| v.as_str().unwrap_or("").trim().to_string())
-            .collect();
+    let peak_rank = optional("peak_rank", &mapping.peak_rank);
+    let teammate_preferences = optional("teammate_preferences", &mapping.teammate_preferences);
+    let roles = optional("roles", &mapping.roles);
+    let attributes = mapping.attributes.iter().filter_map(|column| optional("attributes", &Some(column.clone()))).collect();
 
-        let mut map = serde_json::Map::new();
-        for (key, value) in header_map.iter().zip(row_vals.iter()) {
-            map.insert(key.clone(), serde_json::Value::String(value.clone()));
+    let mut required = |field: &str, column: &Option<ColumnRef>| {
+        let Some(column) = column else {
+            problems.push(problem(1, None, format!("No column is mapped to {}", field), true));
+            return None;
+        };
+        let index = resolve(headers, column);
+        if index.is_none() {
+            problems.push(problem(1, Some(&describe(column)), format!("Column for {} is missing from the header row", field), true));
         }
+        index
+    };
 
-        let player: RawPlayer = serde_json::from_value(serde_json::Value::Object(map))
-            .expect("Could not convert row to RawPlayer");
+    let name = required("name", &mapping.name);
+    let ign = required("ign", &mapping.ign);
+    let current_rank = required("current_rank", &mapping.current_rank);
 
-        players.push(player);
-    }
+    Some(Columns {
+        name: name?,
+        ign: ign?,
+        current_rank: current_rank?,
+        peak_rank,
+        teammate_preferences,
+        roles,
+        attributes,
+    })
+}
+
+/**
+ * Turns a signup table (a header row followed by one row per signup) into
+ * player cards, highest skill rating first, reading fields from the columns in
+ * `mapping` and ranks and roles as `profile` defines them. Rows that can't be
+ * read are skipped and reported rather than failing the whole import.
+ */
+pub fn format_responses(rows: &[Value], mapping: &ColumnMapping, profile: &GameProfile) -> FormattedSignups {
+    let mut signups = FormattedSignups::default();
 
-    let mut player_cards: Vec<PlayerCard> = players
-        .into_iter()
-        .map(|p| PlayerCard {
-            current_rank_order: rank_to_number(&p.current_rank),
-            peak_rank_order: rank_to_number(&p.peak_rank),
-            name: p.name,
-            peak_rank: p.peak_rank,
-            current_rank: p.current_rank,
-            teammate_preferences: p.teammate_preferences,
-            roles: p.roles,
-            ign: p.ign,
+    let Some(headers) = rows.first().and_then(Value::as_array) else {
+        signups.problems.push(problem(1, None, "The signup table has no header row", true));
+        return signups;
+    };
+
+    let header_map: Vec<String> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, cell)| match cell_text(cell) {
+            Some(text) if !text.is_empty() => text,
+            _ => format!("Column {}", index),
         })
         .collect();
 
-    player_cards.sort_by_key(|p| (std::cmp::Reverse(p.current_rank_order), std::cmp::Reverse(p.peak_rank_order)));
+    let Some(columns) = resolve_columns(&header_map, mapping, &mut signups.problems) else {
+        return signups;
+    };
+
+    let ign_column = header_map[columns.ign].clone();
+    let mut seen: Vec<(String, usize)> = vec![];
+
+    for (index, row) in rows.iter().enumerate().skip(1) {
+        let row_number = index + 1;
+
+        let Some(cells) = row.as_array() else {
+            signups.problems.push(problem(row_number, None, "Row is not a list of cells", true));
+            continue;
+        };
+
+        let text: Vec<String> = cells
+            .iter()
+            .zip(header_map.iter())
+            .map(|(cell, header)| cell_text(cell).unwrap_or_else(|| {
+                signups.problems.push(problem(row_number, Some(header), "Cell is not text and was left blank", false));
+                String::new()
+            }))
+            .collect();
+
+        // Blank rows are common at the end of a sheet and aren't worth reporting
+        if text.iter().all(String::is_empty) {
+            continue;
+        }
+
+        // Sheets leaves out trailing empty cells, so short rows are normal
+        let cell = |column: usize| text.get(column).cloned().unwrap_or_default();
+        let optional_cell = |column: Option<usize>| column.map(cell).unwrap_or_default();
+
+        let player = RawPlayer {
+            name: cell(columns.name),
+            ign: cell(columns.ign),
+            current_rank: cell(columns.current_rank),
+            peak_rank: optional_cell(columns.peak_rank),
+            teammate_preferences: optional_cell(columns.teammate_preferences),
+            roles: optional_cell(columns.roles),
+            attributes: columns
+                .attributes
+                .iter()
+                .map(|&column| (header_map[column].clone(), cell(column)))
+                .collect(),
+        };
+
+        if player.ign.is_empty() {
+            signups.problems.push(problem(row_number, Some(&ign_column), "In-game name is blank", true));
+            continue;
+        }
+
+        if let Some((_, first_row)) = seen.iter().find(|(ign, _)| ign == &player.ign) {
+            signups.problems.push(problem(
+                row_number,
+                Some(&ign_column),
+                format!("{} already signed up on row {}", player.ign, first_row),
+                true,
+            ));
+            continue;
+        }
+        seen.push((player.ign.clone(), row_number));
+
+        let mut read_rank = |rank: &str, column: Option<usize>| match parse_rank(profile, rank) {
+            Some(Ok(parsed)) => Some(parsed),
+            Some(Err(())) => {
+                let column = column.map(|column| header_map[column].as_str());
+                signups.problems.push(problem(row_number, column, format!("Could not read the rank \"{}\"", rank), false));
+                None
+            }
+            None => None,
+        };
+
+        let current = read_rank(&player.current_rank, Some(columns.current_rank));
+        let peak = read_rank(&player.peak_rank, columns.peak_rank);
+
+        let (roles, unknown_roles) = normalize_roles(profile, &player.roles);
+        for role in unknown_roles {
+            let column = columns.roles.map(|column| header_map[column].as_str());
+            signups.problems.push(problem(row_number, column, format!("Unknown role \"{}\"", role), false));
+        }
+
+        signups.players.push(PlayerCard {
+            current_rank_order: current.map_or(0, |r| r.tier),
+            peak_rank_order: peak.map_or(0, |r| r.tier),
+            skill_rating: skill_rating(profile, current, peak),
+            name: player.name,
+            peak_rank: player.peak_rank,
+            current_rank: player.current_rank,
+            teammate_preferences: player.teammate_preferences,
+            roles,
+            ign: player.ign,
+            attributes: player.attributes,
+        });
+    }
+
+    signups.players.sort_by(|a, b| b.skill_rating.total_cmp(&a.skill_rating));
+
+    signups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::game_profiles::valorant;
+    use serde_json::json;
+
+    fn mapping() -> ColumnMapping {
+        let header = |text: &str| Some(ColumnRef::Header(text.to_string()));
+
+        ColumnMapping {
+            name: header("Name"),
+            ign: header("IGN"),
+            current_rank: header("Rank"),
+            peak_rank: header("Peak"),
+            teammate_preferences: None,
+            roles: header("Roles"),
+            attributes: vec![ColumnRef::Header("Timezone".to_string())],
+        }
+    }
+
+    fn problems(signups: &FormattedSignups) -> Vec<(usize, Option<&str>, bool)> {
+        signups.problems.iter().map(|p| (p.row, p.column.as_deref(), p.skipped)).collect()
+    }
+
+    #[test]
+    fn reads_signups_highest_rating_first() {
+        let rows = vec![
+            json!(["Name", "IGN", "Rank", "Peak", "Roles", "Timezone"]),
+            json!(["Alice", "Alice#NA1", "Gold 2", "Diamond 1", "duel / smokes", "EST"]),
+            // Sheets drops trailing empty cells
+            json!(["Bob", "Bob#EUW", "Immortal 1"]),
+        ];
+
+        let signups = format_responses(&rows, &mapping(), &valorant());
+
+        assert!(signups.problems.is_empty());
+        let igns: Vec<&str> = signups.players.iter().map(|p| p.ign.as_str()).collect();
+        assert_eq!(igns, ["Bob#EUW", "Alice#NA1"]);
+
+        let alice = &signups.players[1];
+        assert_eq!(alice.roles, "Duelist, Controller");
+        assert_eq!((alice.current_rank_order, alice.peak_rank_order), (4, 6));
+        assert_eq!(alice.skill_rating, 12.5);
+        assert_eq!(alice.attributes.get("Timezone").map(String::as_str), Some("EST"));
+        assert_eq!(signups.players[0].attributes.get("Timezone").map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn reports_problems_per_row() {
+        let rows = vec![
+            json!(["Name", "IGN", "Rank", "Peak", "Roles", "Timezone"]),
+            json!(["Alice", "Alice#NA1", "Gold 2", "", "", ""]),
+            json!(["Carol", "", "Gold 1"]),
+            json!(["Alice again", "Alice#NA1", "Iron 1"]),
+            json!(["Dan", "Dan#123", "Wood 3", "", "Healer"]),
+            json!(["", "", "", "", "", ""]),
+            json!("not a row"),
+            json!(["Eve", "Eve#111", {"rank": "gold"}, "Gold 1"]),
+        ];
 
-    player_cards
-}
\ No newline at end of file
+        let signups = format_responses(&rows, &mapping(), &valorant());
+
+        assert_eq!(problems(&signups), [
+            (3, Some("IGN"), true),
+            (4, Some("IGN"), true),
+            (5, Some("Rank"), false),
+            (5, Some("Roles"), false),
+            (7, None, true),
+            (8, Some("Rank"), false),
+        ]);
+        assert_eq!(signups.problems[1].problem, "Alice#NA1 already signed up on row 2");
+        assert_eq!(signups.problems[2].problem, "Could not read the rank \"Wood 3\"");
+        assert_eq!(signups.problems[3].problem, "Unknown role \"Healer\"");
+
+        // Rows with only partly readable cells are still imported
+        let igns: Vec<&str> = signups.players.iter().map(|p| p.ign.as_str()).collect();
+        assert_eq!(igns, ["Alice#NA1", "Eve#111", "Dan#123"]);
+        assert_eq!(signups.players[2].current_rank_order, 0);
+        assert_eq!(signups.players[1].current_rank, "");
+    }
+
+    #[test]
+    fn missing_required_column_skips_the_table() {
+        let rows = vec![
+            json!(["Name", "Rank", "Peak", "Roles", "Timezone"]),
+            json!(["Alice", "Gold 2", "", "", ""]),
+        ];
+
+        let signups = format_responses(&rows, &mapping(), &valorant());
+
+        assert!(signups.players.is_empty());
+        assert_eq!(problems(&signups), [(1, Some("IGN"), true)]);
+    }
+
+    #[test]
+    fn missing_optional_column_is_left_blank() {
+        let rows = vec![
+            json!(["Name", "IGN", "Rank"]),
+            json!(["Alice", "Alice#NA1", "Gold 2"]),
+        ];
+
+        let signups = format_responses(&rows, &mapping(), &valorant());
+
+        assert_eq!(signups.players.len(), 1);
+        assert_eq!(signups.players[0].peak_rank, "");
+        assert_eq!(problems(&signups), [
+            (1, Some("Peak"), false),
+            (1, Some("Roles"), false),
+            (1, Some("Timezone"), false),
+        ]);
+    }
+
+    #[test]
+    fn index_columns_and_empty_tables() {
+        let by_index = ColumnMapping {
+            name: Some(ColumnRef::Index(0)),
+            ign: Some(ColumnRef::Index(1)),
+            current_rank: Some(ColumnRef::Index(2)),
+            peak_rank: None,
+            teammate_preferences: None,
+            roles: None,
+            attributes: vec![],
+        };
+        let rows = vec![json!(["", "", ""]), json!(["Alice", "Alice#NA1", "Gold 2"])];
+
+        let signups = format_responses(&rows, &by_index, &valorant());
+        assert_eq!(signups.players[0].ign, "Alice#NA1");
+        assert!(signups.problems.is_empty());
+
+        let empty = format_responses(&[], &mapping(), &valorant());
+        assert!(empty.players.is_empty());
+        assert_eq!(problems(&empty), [(1, None, true)]);
+    }
+}
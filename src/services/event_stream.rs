@@ -0,0 +1,162 @@
+use axum::{
+    extract::Extension,
+    http::HeaderMap,
+    response::{IntoResponse, sse::{Event, KeepAlive, Sse}},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::dto::ws_dto::ServerEvent;
+
+/// How many past events are kept for clients resuming with `Last-Event-ID`.
+const REPLAY_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct StreamEvent {
+    pub id: u64,
+    pub event: String,
+    pub data: String,
+}
+
+/**
+ * Numbers the public events the server puts on the broadcast channel and keeps the
+ * most recent ones so SSE clients can pick up where they left off.
+ */
+pub struct EventLog {
+    replay: Mutex<Replay>,
+    tx: broadcast::Sender<StreamEvent>,
+}
+
+struct Replay {
+    events: VecDeque<StreamEvent>,
+    next_id: u64,
+}
+
+pub type SharedEventLog = Arc<EventLog>;
+
+impl EventLog {
+    /**
+     * Creates the log and spawns the task that copies public events from
+     * the draft broadcast channel into it.
+     */
+    pub fn spawn(source: &broadcast::Sender<ServerEvent>) -> SharedEventLog {
+        let (tx, _) = broadcast::channel::<StreamEvent>(REPLAY_CAPACITY);
+        let log = Arc::new(EventLog {
+            replay: Mutex::new(Replay {
+                events: VecDeque::with_capacity(REPLAY_CAPACITY),
+                next_id: 1,
+            }),
+            tx,
+        });
+
+        let mut rx = source.subscribe();
+        let recorder = Arc::clone(&log);
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => recorder.record(event),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Event log fell behind and missed {} messages.", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        log
+    }
+
+    /// Keeps a public event, named by the kind the server gave it.
+    fn record(&self, event: ServerEvent) {
+        if !event.kind.is_public() {
+            return;
+        }
+
+        let mut replay = self.replay.lock().unwrap();
+        let stream_event = StreamEvent { id: replay.next_id, event: event.kind.name().to_string(), data: event.data };
+        replay.next_id += 1;
+
+        if replay.events.len() == REPLAY_CAPACITY {
+            replay.events.pop_front();
+        }
+        replay.events.push_back(stream_event.clone());
+        let _ = self.tx.send(stream_event);
+    }
+
+    fn next_id(&self) -> u64 {
+        self.replay.lock().unwrap().next_id
+    }
+
+    fn since(&self, last_id: u64) -> Vec<StreamEvent> {
+        self.replay
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|e| e.id > last_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/**
+ * GET `/events`: the public draft, team and player updates as `text/event-stream`.
+ * Clients reconnecting with `Last-Event-ID` are replayed anything they missed
+ * that is still in the buffer.
+ */
+pub async fn sse_handler(
+    headers: HeaderMap,
+    Extension(log): Extension<SharedEventLog>,
+) -> impl IntoResponse {
+    let last_id = headers
+        .get("Last-Event-ID")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        // Ids restart with the server, so an id from before a restart cannot be resumed
+        .filter(|id| *id < log.next_id());
+
+    info!("SSE client connected (Last-Event-ID: {:?}).", last_id);
+
+    // Subscribe before reading the backlog so nothing falls between the two
+    let rx = log.tx.subscribe();
+    let backlog = match last_id {
+        Some(id) => log.since(id),
+        None => vec![],
+    };
+    let last_replayed = backlog.last().map(|e| e.id).or(last_id).unwrap_or(0);
+
+    Sse::new(stream::iter(backlog).chain(live_events(rx, last_replayed)).map(to_sse_event))
+        .keep_alive(KeepAlive::default())
+}
+
+/**
+ * Follows the live channel. A lagging client is disconnected rather than
+ * silently skipping events, so its reconnect resumes from the replay buffer.
+ */
+fn live_events(
+    rx: broadcast::Receiver<StreamEvent>,
+    after: u64,
+) -> impl Stream<Item = StreamEvent> {
+    stream::unfold(rx, move |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) if event.id <= after => continue,
+                Ok(event) => return Some((event, rx)),
+                Err(_) => return None,
+            }
+        }
+    })
+}
+
+fn to_sse_event(event: StreamEvent) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .id(event.id.to_string())
+        .event(event.event)
+        .data(event.data))
+}
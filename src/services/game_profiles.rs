@@ -0,0 +1,465 @@
+use sqlx::SqlitePool;
+
+use crate::dto::tournament_dto::{GameProfile, GameRole, RankTier};
+
+fn tier(name: &str, aliases: &[&str], divisions: Option<u8>) -> RankTier {
+    RankTier {
+        name: name.to_string(),
+        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        divisions,
+    }
+}
+
+fn role(name: &str, aliases: &[&str]) -> GameRole {
+    GameRole {
+        name: name.to_string(),
+        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+    }
+}
+
+pub fn valorant() -> GameProfile {
+    GameProfile {
+        game: "Valorant".to_string(),
+        ranks: vec![
+            tier("Iron", &[], None),
+            tier("Bronze", &[], None),
+            tier("Silver", &[], None),
+            tier("Gold", &[], None),
+            tier("Platinum", &["plat"], None),
+            tier("Diamond", &["dia", "dimond"], None),
+            tier("Ascendant", &["asc", "ascendent"], None),
+            tier("Immortal", &["immo", "imm", "immortel"], None),
+            tier("Radiant", &["rad"], Some(0)),
+        ],
+        divisions: 3,
+        division_one_is_highest: false,
+        roles: vec![
+            role("Duelist", &["duel", "entry"]),
+            role("Initiator", &["init"]),
+            role("Controller", &["smokes", "smoker"]),
+            role("Sentinel", &["sent"]),
+            role("Flex", &["fill", "any"]),
+        ],
+    }
+}
+
+pub fn league_of_legends() -> GameProfile {
+    GameProfile {
+        game: "League of Legends".to_string(),
+        ranks: vec![
+            tier("Iron", &[], None),
+            tier("Bronze", &[], None),
+            tier("Silver", &[], None),
+            tier("Gold", &[], None),
+            tier("Platinum", &["plat"], None),
+            tier("Emerald", &["emer", "emmerald"], None),
+            tier("Diamond", &["dia", "dimond"], None),
+            tier("Master", &["masters"], Some(0)),
+            tier("Grandmaster", &["gm"], Some(0)),
+            tier("Challenger", &["chall"], Some(0)),
+        ],
+        divisions: 4,
+        division_one_is_highest: true,
+        roles: vec![
+            role("Top", &["toplane"]),
+            role("Jungle", &["jg", "jungler", "jung"]),
+            role("Mid", &["middle", "midlane"]),
+            role("Bot", &["adc", "bottom", "carry", "marksman"]),
+            role("Support", &["supp", "sup"]),
+            role("Fill", &["flex", "any"]),
+        ],
+    }
+}
+
+pub fn overwatch() -> GameProfile {
+    GameProfile {
+        game: "Overwatch".to_string(),
+        ranks: vec![
+            tier("Bronze", &[], None),
+            tier("Silver", &[], None),
+            tier("Gold", &[], None),
+            tier("Platinum", &["plat"], None),
+            tier("Diamond", &["dia", "dimond"], None),
+            tier("Master", &["masters"], None),
+            tier("Grandmaster", &["gm"], None),
+            tier("Champion", &["champ"], None),
+            tier("Top 500", &["t500"], Some(0)),
+        ],
+        divisions: 5,
+        division_one_is_highest: true,
+        roles: vec![
+            role("Tank", &[]),
+            role("Damage", &["dps", "dmg"]),
+            role("Support", &["supp", "heal", "healer"]),
+            role("Flex", &["fill", "any"]),
+        ],
+    }
+}
+
+/// The built-in profiles an organizer can start from.
+pub fn presets() -> Vec<GameProfile> {
+    vec![valorant(), league_of_legends(), overwatch()]
+}
+
+/// The tournament's game profile, or Valorant's if none was set.
+pub async fn load_game_profile(pool: &SqlitePool) -> Result<GameProfile, String> {
+    let profile = sqlx::query_scalar::<_, Option<String>>("SELECT game_profile FROM tournament WHERE id = 1")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Could not load the tournament settings: {}", e))?;
+
+    match profile {
+        Some(profile) => serde_json::from_str(&profile).map_err(|e| format!("Invalid game profile setting: {}", e)),
+        None => Ok(valorant()),
+    }
+}
+
+/// A rank read from a signup, e.g. "Gold 2" in Valorant is tier 4, division 2.
+#[derive(Debug, Clone, Copy)]
+pub struct ParsedRank {
+    pub tier: u8,
+    pub division: Option<u8>,
+}
+
+fn tier_divisions(profile: &GameProfile, tier: u8) -> u8 {
+    profile.ranks[tier as usize - 1].divisions.unwrap_or(profile.divisions)
+}
+
+/**
+ * A rank in divisions above unranked: the lowest division of the lowest tier
+ * is 1 and each division above it adds 1. Tiers without divisions count as one
+ * step, and a rank given without its division counts as the middle one.
+ */
+pub fn rank_score(profile: &GameProfile, rank: ParsedRank) -> f64 {
+    let below: u32 = (1..rank.tier).map(|tier| tier_divisions(profile, tier).max(1) as u32).sum();
+    let divisions = tier_divisions(profile, rank.tier);
+
+    let step = match rank.division {
+        _ if divisions == 0 => 1,
+        Some(division) if profile.division_one_is_highest => divisions - division + 1,
+        Some(division) => division,
+        None => divisions.div_ceil(2),
+    };
+
+    (below + step as u32) as f64
+}
+
+/// Letters and digits only, lowercased, so "Top 500" and "top500" compare equal.
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Edit distance counting a swap of two neighbouring letters as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/**
+ * Which of `names` a word matches, by index. Each entry is a name followed by
+ * its aliases. With `fuzzy`, words of five or more letters may have one typo,
+ * as long as that doesn't make them match more than one entry.
+ */
+fn match_name(names: &[Vec<String>], word: &str, fuzzy: bool) -> Option<usize> {
+    if let Some(index) = names.iter().position(|aliases| aliases.iter().any(|alias| alias == word)) {
+        return Some(index);
+    }
+
+    if !fuzzy || word.chars().count() < 5 {
+        return None;
+    }
+
+    let close: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, aliases)| aliases.iter().any(|alias| alias.chars().count() >= 4 && edit_distance(word, alias) <= 1))
+        .map(|(index, _)| index)
+        .collect();
+
+    match close.as_slice() {
+        [index] => Some(*index),
+        _ => None,
+    }
+}
+
+fn tier_names(profile: &GameProfile) -> Vec<Vec<String>> {
+    profile
+        .ranks
+        .iter()
+        .map(|tier| std::iter::once(&tier.name).chain(&tier.aliases).map(|name| compact(name)).collect())
+        .collect()
+}
+
+fn match_division(word: &str) -> Option<u8> {
+    match word {
+        "i" => Some(1),
+        "ii" => Some(2),
+        "iii" => Some(3),
+        "iv" => Some(4),
+        "v" => Some(5),
+        _ => word.parse().ok(),
+    }
+}
+
+/// Splits on anything but letters and digits, and between letters and digits ("gold2").
+fn words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut word = String::new();
+
+    for c in text.trim().to_lowercase().chars() {
+        let boundary = word.chars().last().is_some_and(|last| last.is_ascii_digit() != c.is_ascii_digit());
+        if (!c.is_alphanumeric() || boundary) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/**
+ * Reads a rank like "Gold 2", "diamond III", "Plat2", "Grand Master" or
+ * "Immortl 1" against the profile's ladder. Returns `None` for a blank rank
+ * and `Some(Err(()))` for one that can't be read.
+ */
+pub fn parse_rank(profile: &GameProfile, rank: &str) -> Option<Result<ParsedRank, ()>> {
+    let words = words(rank);
+    if words.is_empty() {
+        return None;
+    }
+
+    let names = tier_names(profile);
+
+    // Two words together first, so "grand master" isn't read as "master". Typos
+    // are only allowed in letters, so "diamond3" isn't read as a misspelling.
+    let alphabetic = |word: &String| word.chars().all(char::is_alphabetic);
+    let found = (0..words.len()).find_map(|i| {
+        let pair = words.get(i + 1).map(|next| (format!("{}{}", words[i], next), alphabetic(&words[i]) && alphabetic(next)));
+        pair.and_then(|(pair, fuzzy)| match_name(&names, &pair, fuzzy).map(|tier| (i + 2, tier)))
+            .or_else(|| match_name(&names, &words[i], alphabetic(&words[i])).map(|tier| (i + 1, tier)))
+    });
+
+    let Some((rest, index)) = found else {
+        return Some(Err(()));
+    };
+
+    let tier = index as u8 + 1;
+    let divisions = tier_divisions(profile, tier);
+    let division = words[rest..].iter().find_map(|word| match_division(word));
+
+    match division {
+        Some(division) if divisions > 0 && !(1..=divisions).contains(&division) => Some(Err(())),
+        _ => Some(Ok(ParsedRank { tier, division })),
+    }
+}
+
+/**
+ * One rating from the current and peak rank. Current rank counts most; peak
+ * adds some credit for players coming back from a break.
+ */
+pub fn skill_rating(profile: &GameProfile, current: Option<ParsedRank>, peak: Option<ParsedRank>) -> f64 {
+    let current = current.map(|rank| rank_score(profile, rank));
+    let peak = peak.map(|rank| rank_score(profile, rank));
+
+    let rating = match (current, peak) {
+        (Some(current), Some(peak)) => 0.7 * current + 0.3 * peak.max(current),
+        (Some(current), None) => current,
+        (None, Some(peak)) => peak,
+        (None, None) => 0.0,
+    };
+
+    // Rounded so re-importing the same signups never looks like a change
+    (rating * 100.0).round() / 100.0
+}
+
+/**
+ * Rewrites a signup's role preferences, e.g. "adc / supp", with the profile's
+ * role names, returning them comma separated along with any roles that
+ * weren't recognised. Unrecognised roles are kept as written.
+ */
+pub fn normalize_roles(profile: &GameProfile, roles: &str) -> (String, Vec<String>) {
+    if profile.roles.is_empty() {
+        return (roles.to_string(), vec![]);
+    }
+
+    let names: Vec<Vec<String>> = profile
+        .roles
+        .iter()
+        .map(|role| std::iter::once(&role.name).chain(&role.aliases).map(|name| compact(name)).collect())
+        .collect();
+
+    let mut normalized: Vec<String> = vec![];
+    let mut unknown = vec![];
+
+    for role in roles.split([',', '/', ';', '&', '|', '\n']).map(str::trim).filter(|role| !role.is_empty()) {
+        let name = match match_name(&names, &compact(role), true) {
+            Some(index) => profile.roles[index].name.clone(),
+            None => {
+                unknown.push(role.to_string());
+                role.to_string()
+            }
+        };
+
+        if !normalized.contains(&name) {
+            normalized.push(name);
+        }
+    }
+
+    (normalized.join(", "), unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(profile: &GameProfile, rank: &str) -> (u8, Option<u8>) {
+        let rank = parse_rank(profile, rank).unwrap().unwrap();
+        (rank.tier, rank.division)
+    }
+
+    fn score(profile: &GameProfile, rank: &str) -> f64 {
+        rank_score(profile, parse_rank(profile, rank).unwrap().unwrap())
+    }
+
+    #[test]
+    fn parses_common_spellings() {
+        let valorant = valorant();
+
+        assert_eq!(parsed(&valorant, "Gold 2"), (4, Some(2)));
+        assert_eq!(parsed(&valorant, "diamond III"), (6, Some(3)));
+        assert_eq!(parsed(&valorant, "Plat2"), (5, Some(2)));
+        assert_eq!(parsed(&valorant, "dia 1"), (6, Some(1)));
+        assert_eq!(parsed(&valorant, "Radiant"), (9, None));
+        assert_eq!(parsed(&league_of_legends(), "silver iv"), (3, Some(4)));
+    }
+
+    #[test]
+    fn parses_two_word_ranks() {
+        assert_eq!(parsed(&league_of_legends(), "grand master"), (9, None));
+        assert_eq!(parsed(&league_of_legends(), "Master"), (8, None));
+        assert_eq!(parsed(&overwatch(), "Top 500"), (9, None));
+        assert_eq!(parsed(&overwatch(), "t500"), (9, None));
+    }
+
+    #[test]
+    fn allows_one_typo_in_long_words() {
+        let valorant = valorant();
+
+        assert_eq!(parsed(&valorant, "Immortl 1"), (8, Some(1)));
+        assert_eq!(parsed(&valorant, "Acsendant 3"), (7, Some(3)));
+        // Too short to guess at
+        assert!(parse_rank(&valorant, "Golf 2").unwrap().is_err());
+    }
+
+    #[test]
+    fn refuses_ambiguous_typos() {
+        let profile = GameProfile {
+            game: "Test".to_string(),
+            ranks: vec![tier("Ranger", &[], None), tier("Danger", &[], None)],
+            divisions: 3,
+            division_one_is_highest: false,
+            roles: vec![],
+        };
+
+        assert_eq!(parsed(&profile, "Rangr 1"), (1, Some(1)));
+        assert!(parse_rank(&profile, "Banger 1").unwrap().is_err());
+    }
+
+    #[test]
+    fn rejects_blank_unknown_and_out_of_range_ranks() {
+        let valorant = valorant();
+
+        assert!(parse_rank(&valorant, "").is_none());
+        assert!(parse_rank(&valorant, "   ").is_none());
+        assert!(parse_rank(&valorant, "Unranked").unwrap().is_err());
+        assert!(parse_rank(&valorant, "Gold 4").unwrap().is_err());
+        assert!(parse_rank(&valorant, "Gold 0").unwrap().is_err());
+    }
+
+    #[test]
+    fn scores_divisions_in_the_profiles_direction() {
+        let valorant = valorant();
+        assert_eq!(score(&valorant, "Iron 1"), 1.0);
+        assert_eq!(score(&valorant, "Gold 2"), 11.0);
+        assert!(score(&valorant, "Gold 3") > score(&valorant, "Gold 1"));
+        // Without a division the middle one is assumed
+        assert_eq!(score(&valorant, "Gold"), 11.0);
+        assert_eq!(score(&valorant, "Radiant"), 25.0);
+
+        let league = league_of_legends();
+        assert_eq!(score(&league, "Gold 1"), 16.0);
+        assert_eq!(score(&league, "Gold 4"), 13.0);
+        assert!(score(&league, "Emerald 4") > score(&league, "Platinum 1"));
+        assert_eq!(score(&league, "Master"), 29.0);
+        assert_eq!(score(&league, "Grandmaster"), 30.0);
+        assert_eq!(score(&league, "Challenger"), 31.0);
+
+        assert_eq!(score(&overwatch(), "Top 500"), 41.0);
+    }
+
+    #[test]
+    fn skill_rating_weights_current_over_peak() {
+        let valorant = valorant();
+        let rank = |text| parse_rank(&valorant, text).unwrap().ok();
+
+        assert_eq!(skill_rating(&valorant, rank("Gold 2"), rank("Diamond 1")), 12.5);
+        // A peak below the current rank never lowers the rating
+        assert_eq!(skill_rating(&valorant, rank("Diamond 1"), rank("Gold 2")), 16.0);
+        assert_eq!(skill_rating(&valorant, rank("Gold 2"), None), 11.0);
+        assert_eq!(skill_rating(&valorant, None, rank("Gold 2")), 11.0);
+        assert_eq!(skill_rating(&valorant, None, None), 0.0);
+        assert_eq!(skill_rating(&valorant, rank("Iron 1"), rank("Iron 2")), 1.3);
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_as_one() {
+        assert_eq!(edit_distance("gold", "gold"), 0);
+        assert_eq!(edit_distance("immortl", "immortal"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn normalizes_roles_to_profile_names() {
+        let valorant = valorant();
+
+        assert_eq!(normalize_roles(&valorant, "duel / smokes"), ("Duelist, Controller".to_string(), vec![]));
+        assert_eq!(normalize_roles(&valorant, "Initator, Sentinel"), ("Initiator, Sentinel".to_string(), vec![]));
+        assert_eq!(normalize_roles(&valorant, "Duelist, duel"), ("Duelist".to_string(), vec![]));
+        assert_eq!(
+            normalize_roles(&valorant, "Healer & Duelist"),
+            ("Healer, Duelist".to_string(), vec!["Healer".to_string()])
+        );
+        assert_eq!(normalize_roles(&league_of_legends(), "adc/supp"), ("Bot, Support".to_string(), vec![]));
+        assert_eq!(normalize_roles(&valorant, ""), (String::new(), vec![]));
+
+        let no_roles = GameProfile { roles: vec![], ..valorant };
+        assert_eq!(normalize_roles(&no_roles, "anything / goes"), ("anything / goes".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::Utc;
+use rand::{rng, Rng};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use tracing::info;
+
+use crate::dto::invite_dto::Invite;
+use crate::services::rbac::Role;
+
+/// Letters and digits that cannot be confused with each other when read aloud or typed.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 10;
+
+pub fn generate_code() -> String {
+    let mut rng = rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+pub async fn create_invite(
+    pool: &SqlitePool,
+    player_ign: Option<&str>,
+    role: Role,
+    created_by: &str,
+) -> Result<Invite, sqlx::Error> {
+    let code = generate_code();
+    let created_at = Utc::now().to_rfc3339();
+
+    sqlx::query_as::<_, Invite>(
+        r#"
+        INSERT INTO invites (code, player_ign, role, created_by, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *
+        "#
+    )
+    .bind(code)
+    .bind(player_ign)
+    .bind(role)
+    .bind(created_by)
+    .bind(created_at)
+    .fetch_one(pool)
+    .await
+}
+
+/**
+ * Issues a player invite for every signup that has neither an invite nor a
+ * linked account yet, returning the new invites.
+ */
+pub async fn issue_player_invites(pool: &SqlitePool, created_by: &str) -> Result<Vec<Invite>, sqlx::Error> {
+    let igns = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT ign FROM players
+        WHERE ign NOT IN (SELECT player_ign FROM invites WHERE player_ign IS NOT NULL)
+          AND ign NOT IN (SELECT player_ign FROM users WHERE player_ign IS NOT NULL)
+        ORDER BY ign
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut invites = Vec::with_capacity(igns.len());
+    for ign in igns {
+        invites.push(create_invite(pool, Some(&ign), Role::Player, created_by).await?);
+    }
+
+    Ok(invites)
+}
+
+/**
+ * Marks an invite as used by `username` inside the registration transaction.
+ * Returns `None` if the code does not exist or was already used.
+ */
+pub async fn redeem_invite(
+    tx: &mut Transaction<'_, Sqlite>,
+    code: &str,
+    username: &str,
+) -> Result<Option<Invite>, sqlx::Error> {
+    let used_at = Utc::now().to_rfc3339();
+
+    sqlx::query_as::<_, Invite>(
+        r#"
+        UPDATE invites SET used_by = ?, used_at = ?
+        WHERE code = ? AND used_by IS NULL
+        RETURNING *
+        "#
+    )
+    .bind(username)
+    .bind(used_at)
+    .bind(code.trim().to_uppercase())
+    .fetch_optional(&mut **tx)
+    .await
+}
+
+/**
+ * On a fresh database nobody can create invites, so when there is no admin
+ * and no unused admin invite, one is created and its code logged at startup.
+ */
+pub async fn ensure_bootstrap_invite(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let admins = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT (SELECT COUNT(*) FROM users WHERE role = 'admin')
+             + (SELECT COUNT(*) FROM invites WHERE role = 'admin' AND used_by IS NULL)
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if admins == 0 {
+        let invite = create_invite(pool, None, Role::Admin, "system").await?;
+        info!("No admin account exists. Register with invite code {} to create one.", invite.code);
+    }
+
+    Ok(())
+}
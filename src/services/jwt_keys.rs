@@ -0,0 +1,185 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, sync::Arc};
+
+use crate::dto::claims_dto::Claims;
+use crate::services::rbac::Role;
+use crate::services::sessions::random_token;
+
+/**
+ * The JWT key configuration read from `jwt_keys.json` (or `JWT_KEYS_FILE`).
+ *
+ * ```json
+ * {
+ *   "issuer": "draft-pick-backend",
+ *   "audience": "draft-pick",
+ *   "signing_kid": "2026-10",
+ *   "keys": [
+ *     { "kid": "2026-10", "algorithm": "EdDSA", "private_key_file": "keys/2026-10.pem", "public_key_file": "keys/2026-10.pub.pem" },
+ *     { "kid": "2026-04", "algorithm": "RS256", "public_key_file": "keys/2026-04.pub.pem" }
+ *   ]
+ * }
+ * ```
+ *
+ * Every listed key is accepted for verification, so a retired signing key can
+ * stay in the list (public half only) until the tokens it issued expire.
+ */
+#[derive(Debug, Deserialize)]
+struct KeysFile {
+    issuer: String,
+    audience: String,
+    signing_kid: String,
+    keys: Vec<KeyEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyEntry {
+    kid: String,
+    algorithm: Algorithm,
+    /// Shared secret for HS* keys.
+    secret: Option<String>,
+    /// PEM private key for RS*, PS*, ES* and EdDSA keys. Only needed on the signing key.
+    private_key_file: Option<String>,
+    /// PEM public key for RS*, PS*, ES* and EdDSA keys.
+    public_key_file: Option<String>,
+}
+
+pub struct JwtKeys {
+    issuer: String,
+    audience: String,
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verification_keys: HashMap<String, (Algorithm, DecodingKey)>,
+}
+
+pub type SharedJwtKeys = Arc<JwtKeys>;
+
+impl JwtKeys {
+    pub fn from_env() -> Result<Self, String> {
+        let path = env::var("JWT_KEYS_FILE").unwrap_or_else(|_| "jwt_keys.json".to_string());
+        let contents = fs::read_to_string(&path).map_err(|e| format!(
+            "Could not read JWT key file {} ({}). Copy jwt_keys.example.json there or point JWT_KEYS_FILE at your own; see \"JWT keys\" in the README.",
+            path, e
+        ))?;
+        let file: KeysFile = serde_json::from_str(&contents).map_err(|e| format!(
+            "Could not parse JWT key file {} ({}). It needs issuer, audience, signing_kid and a keys list; see jwt_keys.example.json.",
+            path, e
+        ))?;
+
+        Self::from_keys_file(file)
+    }
+
+    fn from_keys_file(file: KeysFile) -> Result<Self, String> {
+        let mut verification_keys = HashMap::new();
+        let mut signing = None;
+
+        for entry in &file.keys {
+            verification_keys.insert(entry.kid.clone(), (entry.algorithm, decoding_key(entry)?));
+
+            if entry.kid == file.signing_kid {
+                signing = Some((entry.algorithm, encoding_key(entry)?));
+            }
+        }
+
+        let (signing_algorithm, signing_key) = signing
+            .ok_or(format!("Signing key '{}' is not in the key list", file.signing_kid))?;
+
+        Ok(Self {
+            issuer: file.issuer,
+            audience: file.audience,
+            signing_kid: file.signing_kid,
+            signing_algorithm,
+            signing_key,
+            verification_keys,
+        })
+    }
+
+    /**
+     * Issues a token for `sub` signed with the current signing key, tagged with its
+     * `kid`. Each token gets a random `jti` so it can be revoked on its own.
+     */
+    pub fn issue(&self, sub: &str, role: Role, ttl: Duration) -> Result<String, Error> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: sub.to_string(),
+            exp: (now + ttl).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            iat_ms: now.timestamp_millis(),
+            jti: random_token(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            role,
+        };
+
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = Some(self.signing_kid.clone());
+
+        encode(&header, &claims, &self.signing_key)
+    }
+
+    /**
+     * Verifies a token against the key named by its `kid` header, checking the
+     * expiry, issuer and audience.
+     */
+    pub fn verify(&self, token: &str) -> Result<Claims, Error> {
+        let header = decode_header(token)?;
+        let (algorithm, key) = header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.verification_keys.get(kid))
+            .ok_or(Error::from(ErrorKind::InvalidKeyFormat))?;
+
+        let mut validation = Validation::new(*algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+
+        Ok(decode::<Claims>(token, key, &validation)?.claims)
+    }
+}
+
+fn encoding_key(entry: &KeyEntry) -> Result<EncodingKey, String> {
+    use Algorithm::*;
+
+    let key = match entry.algorithm {
+        HS256 | HS384 | HS512 => Ok(EncodingKey::from_secret(secret(entry)?.as_bytes())),
+        RS256 | RS384 | RS512 | PS256 | PS384 | PS512 => EncodingKey::from_rsa_pem(&pem(entry, &entry.private_key_file)?),
+        ES256 | ES384 => EncodingKey::from_ec_pem(&pem(entry, &entry.private_key_file)?),
+        EdDSA => EncodingKey::from_ed_pem(&pem(entry, &entry.private_key_file)?),
+    };
+
+    key.map_err(|e| format!("Invalid private key for '{}': {}", entry.kid, e))
+}
+
+fn decoding_key(entry: &KeyEntry) -> Result<DecodingKey, String> {
+    use Algorithm::*;
+
+    let key = match entry.algorithm {
+        HS256 | HS384 | HS512 => Ok(DecodingKey::from_secret(secret(entry)?.as_bytes())),
+        RS256 | RS384 | RS512 | PS256 | PS384 | PS512 => DecodingKey::from_rsa_pem(&pem(entry, &entry.public_key_file)?),
+        ES256 | ES384 => DecodingKey::from_ec_pem(&pem(entry, &entry.public_key_file)?),
+        EdDSA => DecodingKey::from_ed_pem(&pem(entry, &entry.public_key_file)?),
+    };
+
+    key.map_err(|e| format!("Invalid public key for '{}': {}", entry.kid, e))
+}
+
+fn secret(entry: &KeyEntry) -> Result<&str, String> {
+    entry
+        .secret
+        .as_deref()
+        .ok_or(format!("Key '{}' needs a secret", entry.kid))
+}
+
+fn pem(entry: &KeyEntry, path: &Option<String>) -> Result<Vec<u8>, String> {
+    let path = path
+        .as_deref()
+        .ok_or(format!("Key '{}' is missing a PEM file", entry.kid))?;
+
+    fs::read(path).map_err(|e| format!("Could not read {} for key '{}': {}", path, entry.kid, e))
+}
@@ -0,0 +1,119 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use std::net::IpAddr;
+
+/// Failures allowed before an account is locked out.
+const ACCOUNT_THRESHOLD: i64 = 5;
+/// Failures allowed from one address before it is locked out. Higher than the
+/// account threshold since several people may share an address at a venue.
+const IP_THRESHOLD: i64 = 20;
+/// The first lockout lasts this long and doubles with every further failure.
+const BASE_LOCKOUT_SECS: i64 = 30;
+const MAX_LOCKOUT_SECS: i64 = 60 * 60;
+
+pub fn account_key(username: &str) -> String {
+    format!("account:{}", username.to_lowercase())
+}
+
+pub fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+/**
+ * Returns how many seconds remain on the longest active lockout among `keys`,
+ * or `None` if none of them are locked.
+ */
+pub async fn locked_for(pool: &SqlitePool, keys: &[&str]) -> Result<Option<i64>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+    let mut remaining = None;
+
+    for key in keys {
+        let locked_until = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT locked_until FROM login_throttle WHERE key = ?"
+        )
+        .bind(key)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+        if let Some(until) = locked_until.filter(|until| *until > now) {
+            remaining = remaining.max(Some(until - now));
+        }
+    }
+
+    Ok(remaining)
+}
+
+/**
+ * Records a failed login against the account and the address, locking either
+ * out once it passes its threshold.
+ */
+pub async fn record_failure(pool: &SqlitePool, username: &str, ip: IpAddr) -> Result<(), sqlx::Error> {
+    let account = account_key(username);
+    bump(pool, &account, ACCOUNT_THRESHOLD).await?;
+
+    sqlx::query("UPDATE login_throttle SET last_ip = ? WHERE key = ?")
+        .bind(ip.to_string())
+        .bind(&account)
+        .execute(pool)
+        .await?;
+
+    bump(pool, &ip_key(ip), IP_THRESHOLD).await
+}
+
+async fn bump(pool: &SqlitePool, key: &str, threshold: i64) -> Result<(), sqlx::Error> {
+    let failures = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO login_throttle (key, failures) VALUES (?, 1)
+        ON CONFLICT(key) DO UPDATE SET failures = failures + 1
+        RETURNING failures
+        "#
+    )
+    .bind(key)
+    .fetch_one(pool)
+    .await?;
+
+    if failures >= threshold {
+        let exponent = (failures - threshold).min(16) as u32;
+        let lockout = (BASE_LOCKOUT_SECS * 2i64.pow(exponent)).min(MAX_LOCKOUT_SECS);
+        let locked_until = Utc::now().timestamp() + lockout;
+
+        sqlx::query("UPDATE login_throttle SET locked_until = ? WHERE key = ?")
+            .bind(locked_until)
+            .bind(key)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Forgets failures for a key, e.g. after a successful login or an admin unlock.
+pub async fn clear(pool: &SqlitePool, key: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM login_throttle WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/**
+ * Forgets failures for an account and for the address its last failure came
+ * from, so an admin unlock isn't undone by a still locked address.
+ */
+pub async fn unlock(pool: &SqlitePool, username: &str) -> Result<bool, sqlx::Error> {
+    let account = account_key(username);
+    let last_ip = sqlx::query_scalar::<_, Option<String>>("SELECT last_ip FROM login_throttle WHERE key = ?")
+        .bind(&account)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    let mut cleared = clear(pool, &account).await?;
+    if let Some(ip) = last_ip.and_then(|ip| ip.parse().ok()) {
+        cleared |= clear(pool, &ip_key(ip)).await?;
+    }
+
+    Ok(cleared)
+}
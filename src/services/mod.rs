@@ -1,3 +1,21 @@
 pub mod draft_player_formatter;
 pub mod auth_user;
-pub mod websocket;
\ No newline at end of file
+pub mod websocket;
+pub mod ws_connections;
+pub mod event_stream;
+pub mod channels;
+pub mod chat;
+pub mod password;
+pub mod jwt_keys;
+pub mod rbac;
+pub mod sessions;
+pub mod login_throttle;
+pub mod invites;
+
+pub mod oauth;
+pub mod password_resets;
+pub mod audit;
+pub mod validation;
+pub mod player_sources;
+pub mod player_import;
+pub mod game_profiles;
\ No newline at end of file
@@ -0,0 +1,330 @@
+use axum::http::{header::COOKIE, HeaderMap};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::{rng, Rng};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::{collections::HashMap, env, fs, sync::Arc};
+use tracing::{info, warn};
+
+use crate::services::sessions::{hex, random_token};
+
+/// How long a login started with `authorize_url` may take to come back.
+fn state_ttl() -> Duration {
+    Duration::minutes(10)
+}
+
+/// The cookie that ties a login in progress to the browser that started it.
+const STATE_COOKIE: &str = "oauth_state";
+
+/**
+ * The OAuth2 providers read from `oauth.json` (or `OAUTH_CONFIG_FILE`). Without
+ * the file, OAuth login is disabled.
+ *
+ * ```json
+ * {
+ *   "providers": [
+ *     {
+ *       "name": "discord",
+ *       "client_id": "...",
+ *       "client_secret": "...",
+ *       "authorize_url": "https://discord.com/oauth2/authorize",
+ *       "token_url": "https://discord.com/api/oauth2/token",
+ *       "userinfo_url": "https://discord.com/api/users/@me",
+ *       "redirect_uri": "https://draft.example.com/oauth/discord",
+ *       "scopes": ["identify"],
+ *       "subject_field": "id",
+ *       "username_field": "username",
+ *       "display_name_field": "global_name"
+ *     }
+ *   ]
+ * }
+ * ```
+ *
+ * The `*_field` entries name the userinfo fields to read and default to the
+ * OIDC claims `sub`, `preferred_username` and `name`, so a local mock server
+ * (see `examples/mock_oauth_provider.rs`) can stand in for Discord.
+ *
+ * The state cookie is signed with `OAUTH_COOKIE_SECRET`. Without it a random
+ * key is used, so logins in progress across a restart have to start over.
+ */
+#[derive(Debug, Deserialize)]
+struct ProvidersFile {
+    providers: Vec<OAuthProvider>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthProvider {
+    pub name: String,
+    client_id: String,
+    client_secret: String,
+    authorize_url: String,
+    token_url: String,
+    userinfo_url: String,
+    redirect_uri: String,
+    #[serde(default)]
+    scopes: Vec<String>,
+    #[serde(default = "default_subject_field")]
+    subject_field: String,
+    #[serde(default = "default_username_field")]
+    username_field: String,
+    #[serde(default = "default_display_name_field")]
+    display_name_field: String,
+}
+
+fn default_subject_field() -> String {
+    "sub".to_string()
+}
+
+fn default_username_field() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_display_name_field() -> String {
+    "name".to_string()
+}
+
+/// Who the provider says signed in.
+pub struct ExternalIdentity {
+    pub subject: String,
+    pub username: String,
+    pub display_name: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+pub struct OAuthProviders {
+    providers: HashMap<String, OAuthProvider>,
+    client: Client,
+    cookie_key: Vec<u8>,
+}
+
+pub type SharedOAuthProviders = Arc<OAuthProviders>;
+
+impl OAuthProviders {
+    pub fn from_env() -> Result<Self, String> {
+        let cookie_key = match env::var("OAUTH_COOKIE_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ => {
+                let mut key = vec![0u8; 32];
+                rng().fill(&mut key[..]);
+                key
+            }
+        };
+
+        let path = env::var("OAUTH_CONFIG_FILE").unwrap_or_else(|_| "oauth.json".to_string());
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                info!("No OAuth config at {}; OAuth login is disabled.", path);
+                return Ok(Self { providers: HashMap::new(), client: Client::new(), cookie_key });
+            }
+        };
+        let file: ProvidersFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("Could not parse OAuth config {}: {}", path, e))?;
+
+        let mut providers = HashMap::new();
+        for provider in file.providers {
+            Url::parse(&provider.authorize_url)
+                .map_err(|e| format!("Invalid authorize_url for '{}': {}", provider.name, e))?;
+            info!("OAuth login enabled for {}.", provider.name);
+            providers.insert(provider.name.clone(), provider);
+        }
+
+        if env::var("OAUTH_COOKIE_SECRET").is_err() && !providers.is_empty() {
+            warn!("OAUTH_COOKIE_SECRET is not set; using a random key for OAuth state cookies.");
+        }
+
+        Ok(Self { providers, client: Client::new(), cookie_key })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OAuthProvider> {
+        self.providers.get(name)
+    }
+
+    fn state_mac(&self, state: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.cookie_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(state.as_bytes());
+        mac
+    }
+
+    /**
+     * The `Set-Cookie` value that binds `state` to this browser. The cookie is
+     * HttpOnly and signed, so page scripts can't read it and nobody can plant one
+     * for a state they were handed.
+     */
+    pub fn state_cookie(&self, state: &str) -> String {
+        let signature = hex(&self.state_mac(state).finalize().into_bytes());
+        format!(
+            "{}={}.{}; Path=/oauth; Max-Age={}; HttpOnly; Secure; SameSite=None",
+            STATE_COOKIE, state, signature, state_ttl().num_seconds()
+        )
+    }
+
+    /// The `Set-Cookie` value that removes the state cookie once the login is done.
+    pub fn clear_state_cookie(&self) -> String {
+        format!("{}=; Path=/oauth; Max-Age=0; HttpOnly; Secure; SameSite=None", STATE_COOKIE)
+    }
+
+    /// Whether the request carries a validly signed state cookie for `state`.
+    pub fn has_state_cookie(&self, headers: &HeaderMap, state: &str) -> bool {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|cookie| cookie.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+            .filter_map(|value| value.split_once('.'))
+            .any(|(cookie_state, signature)| {
+                cookie_state == state
+                    && decode_hex(signature).is_some_and(|bytes| self.state_mac(state).verify_slice(&bytes).is_ok())
+            })
+    }
+
+    /**
+     * Exchanges an authorization code for an access token and reads the
+     * user's identity from the provider's userinfo endpoint.
+     */
+    pub async fn fetch_identity(&self, provider: &OAuthProvider, code: &str) -> Result<ExternalIdentity, String> {
+        let token: TokenResponse = self.client
+            .post(&provider.token_url)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", provider.redirect_uri.as_str()),
+                ("client_id", provider.client_id.as_str()),
+                ("client_secret", provider.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Token exchange failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+
+        let userinfo: Value = self.client
+            .get(&provider.userinfo_url)
+            .bearer_auth(&token.access_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Userinfo request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid userinfo response: {}", e))?;
+
+        let field = |name: &str| match userinfo.get(name) {
+            Some(Value::String(value)) if !value.is_empty() => Some(value.clone()),
+            Some(Value::Number(value)) => Some(value.to_string()),
+            _ => None,
+        };
+
+        let subject = field(&provider.subject_field)
+            .ok_or(format!("Userinfo has no '{}' field", provider.subject_field))?;
+        let username = field(&provider.username_field).unwrap_or_else(|| subject.clone());
+        let display_name = field(&provider.display_name_field).unwrap_or_else(|| username.clone());
+
+        Ok(ExternalIdentity { subject, username, display_name })
+    }
+}
+
+impl OAuthProvider {
+    pub fn authorize_url(&self, state: &str) -> String {
+        let scopes = self.scopes.join(" ");
+        let params = [
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("scope", scopes.as_str()),
+            ("state", state),
+        ];
+
+        // Checked in `from_env`
+        Url::parse_with_params(&self.authorize_url, &params)
+            .map(String::from)
+            .unwrap_or_default()
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// What the login was started for, recovered from its `state`.
+pub struct PendingLogin {
+    pub invite_code: Option<String>,
+    pub link_username: Option<String>,
+}
+
+/**
+ * Records a login in progress and returns the `state` value that ties the
+ * provider's redirect back to it.
+ */
+pub async fn create_state(
+    pool: &SqlitePool,
+    provider: &str,
+    invite_code: Option<&str>,
+    link_username: Option<&str>,
+) -> Result<String, sqlx::Error> {
+    let state = random_token();
+    let now = Utc::now();
+    let expires_at = (now + state_ttl()).timestamp();
+
+    sqlx::query("DELETE FROM oauth_states WHERE expires_at <= ?")
+        .bind(now.timestamp())
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO oauth_states (state, provider, invite_code, link_username, expires_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&state)
+    .bind(provider)
+    .bind(invite_code)
+    .bind(link_username)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(state)
+}
+
+/**
+ * Consumes a `state`, so each one can complete a single login. Returns `None`
+ * if it is unknown, expired, or belongs to another provider.
+ */
+pub async fn take_state(pool: &SqlitePool, provider: &str, state: &str) -> Result<Option<PendingLogin>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+
+    let pending = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        r#"
+        DELETE FROM oauth_states
+        WHERE state = ? AND provider = ? AND expires_at > ?
+        RETURNING invite_code, link_username
+        "#
+    )
+    .bind(state)
+    .bind(provider)
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(pending.map(|(invite_code, link_username)| PendingLogin { invite_code, link_username }))
+}
@@ -0,0 +1,57 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::{rng, Rng};
+use std::sync::LazyLock;
+
+/// A real hash to verify against when the account does not exist, so a
+/// missing username takes as long to reject as a wrong password.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash_password("not a real password").expect("Could not hash dummy password")
+});
+
+/**
+ * Hashes a password with Argon2id, returning the PHC string stored in `users.password`.
+ */
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let mut salt_bytes = [0u8; 16];
+    rng().fill(&mut salt_bytes);
+    let salt = SaltString::encode_b64(&salt_bytes)?;
+    let hash = Argon2::default().hash_password(password.as_bytes(), &salt)?;
+
+    Ok(hash.to_string())
+}
+
+/**
+ * Checks a password against the stored value. Rows created before hashing was
+ * introduced hold the plaintext password; those are compared in constant time
+ * and should be rehashed by the caller once the login succeeds.
+ */
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => constant_time_eq(password.as_bytes(), stored.as_bytes()),
+    }
+}
+
+/// Spends the same time as `verify_password` against a real hash, always failing.
+pub fn verify_dummy(password: &str) -> bool {
+    verify_password(password, &DUMMY_HASH);
+    false
+}
+
+/// True if the stored value is a legacy plaintext password rather than a PHC hash.
+pub fn is_legacy(stored: &str) -> bool {
+    PasswordHash::new(stored).is_err()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,61 @@
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+use crate::services::invites::generate_code;
+use crate::services::sessions::hash_token;
+
+/// Reset codes are handed over out of band, so they stay valid for a day.
+fn reset_code_ttl() -> Duration {
+    Duration::hours(24)
+}
+
+/**
+ * Issues a one-time password reset code for a user, replacing any earlier
+ * unused code. Only the hash is stored.
+ */
+pub async fn create_reset_code(pool: &SqlitePool, user_id: i64, created_by: &str) -> Result<String, sqlx::Error> {
+    let code = generate_code();
+    let expires_at = (Utc::now() + reset_code_ttl()).timestamp();
+
+    sqlx::query("DELETE FROM password_resets WHERE user_id = ? AND used = 0")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO password_resets (user_id, code_hash, created_by, expires_at, used)
+        VALUES (?, ?, ?, ?, 0)
+        "#
+    )
+    .bind(user_id)
+    .bind(hash_token(&code))
+    .bind(created_by)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(code)
+}
+
+/**
+ * Marks a reset code for `username` as used, returning the user's id, or
+ * `None` if the code is wrong, used or expired.
+ */
+pub async fn redeem_reset_code(pool: &SqlitePool, username: &str, code: &str) -> Result<Option<i64>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE password_resets SET used = 1
+        WHERE code_hash = ? AND used = 0 AND expires_at > ?
+          AND user_id = (SELECT id FROM users WHERE username = ?)
+        RETURNING user_id
+        "#
+    )
+    .bind(hash_token(&code.trim().to_uppercase()))
+    .bind(now)
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,230 @@
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+use crate::dto::import_dto::{FieldChange, ImportDiff, PlayerChange};
+use crate::dto::player_dto::{Player, PlayerCard};
+
+/// A preview is meant to be reviewed and committed in one sitting.
+fn preview_ttl() -> Duration {
+    Duration::hours(1)
+}
+
+/**
+ * Stores the signups read for a dry-run import and returns the preview's id.
+ * Expired previews are cleared out at the same time.
+ */
+pub async fn store_preview(pool: &SqlitePool, created_by: &str, signups: &[PlayerCard]) -> Result<i64, sqlx::Error> {
+    let now = Utc::now();
+    let signups = serde_json::to_string(signups).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    sqlx::query("DELETE FROM player_import_previews WHERE expires_at <= ?")
+        .bind(now.timestamp())
+        .execute(pool)
+        .await?;
+
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO player_import_previews (created_by, signups, expires_at)
+        VALUES (?, ?, ?)
+        RETURNING id
+        "#
+    )
+    .bind(created_by)
+    .bind(signups)
+    .bind((now + preview_ttl()).timestamp())
+    .fetch_one(pool)
+    .await
+}
+
+/// The signups of a preview, or `None` if it does not exist or has expired.
+pub async fn load_preview(pool: &SqlitePool, preview_id: i64) -> Result<Option<Vec<PlayerCard>>, sqlx::Error> {
+    let signups = sqlx::query_scalar::<_, String>(
+        "SELECT signups FROM player_import_previews WHERE id = ? AND expires_at > ?"
+    )
+    .bind(preview_id)
+    .bind(Utc::now().timestamp())
+    .fetch_optional(pool)
+    .await?;
+
+    signups
+        .map(|signups| serde_json::from_str(&signups).map_err(|e| sqlx::Error::Decode(Box::new(e))))
+        .transpose()
+}
+
+/// Deletes a committed preview so it cannot be applied twice.
+pub async fn discard_preview(pool: &SqlitePool, preview_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM player_import_previews WHERE id = ?")
+        .bind(preview_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn field_changes(existing: &Player, incoming: &PlayerCard) -> Vec<FieldChange> {
+    let fields = [
+        ("name", existing.name.clone(), incoming.name.clone()),
+        ("peak_rank", existing.peak_rank.clone(), incoming.peak_rank.clone()),
+        ("current_rank", existing.current_rank.clone(), incoming.current_rank.clone()),
+        (
+            "teammate_preferences",
+            existing.teammate_preferences.clone().unwrap_or_default(),
+            incoming.teammate_preferences.clone(),
+        ),
+        ("roles", existing.roles.clone().unwrap_or_default(), incoming.roles.clone()),
+        ("current_rank_order", existing.current_rank_order.to_string(), incoming.current_rank_order.to_string()),
+        ("peak_rank_order", existing.peak_rank_order.to_string(), incoming.peak_rank_order.to_string()),
+        ("skill_rating", existing.skill_rating.to_string(), incoming.skill_rating.to_string()),
+        (
+            "attributes",
+            serde_json::to_string(&existing.attributes).unwrap_or_default(),
+            serde_json::to_string(&incoming.attributes).unwrap_or_default(),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| FieldChange { field: field.to_string(), old, new })
+        .collect()
+}
+
+/**
+ * Compares signups read from the player source with the player pool, matching
+ * players by IGN.
+ */
+pub fn diff_signups(preview_id: i64, existing: &[Player], incoming: &[PlayerCard]) -> ImportDiff {
+    let mut diff = ImportDiff {
+        preview_id,
+        new_players: vec![],
+        changed: vec![],
+        missing: vec![],
+        unchanged: 0,
+        problems: vec![],
+    };
+
+    for card in incoming {
+        match existing.iter().find(|player| player.ign == card.ign) {
+            None => diff.new_players.push(card.clone()),
+            Some(player) => {
+                let changes = field_changes(player, card);
+                if changes.is_empty() {
+                    diff.unchanged += 1;
+                } else {
+                    diff.changed.push(PlayerChange { ign: card.ign.clone(), changes });
+                }
+            }
+        }
+    }
+
+    diff.missing = existing
+        .iter()
+        .filter(|player| !incoming.iter().any(|card| card.ign == player.ign))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::Json;
+
+    fn card(ign: &str, current_rank: &str, current_rank_order: u8) -> PlayerCard {
+        PlayerCard {
+            name: ign.split('#').next().unwrap_or_default().to_string(),
+            peak_rank: String::new(),
+            current_rank: current_rank.to_string(),
+            teammate_preferences: String::new(),
+            roles: "Duelist".to_string(),
+            ign: ign.to_string(),
+            current_rank_order,
+            peak_rank_order: 0,
+            skill_rating: current_rank_order as f64,
+            attributes: Default::default(),
+        }
+    }
+
+    /// The player the pool would hold after importing `card`.
+    fn player(card: &PlayerCard, drafted: bool) -> Player {
+        Player {
+            name: card.name.clone(),
+            peak_rank: card.peak_rank.clone(),
+            current_rank: card.current_rank.clone(),
+            // Older rows may have NULL where the import now writes ""
+            teammate_preferences: None,
+            roles: Some(card.roles.clone()),
+            ign: card.ign.clone(),
+            current_rank_order: card.current_rank_order as i64,
+            peak_rank_order: card.peak_rank_order as i64,
+            drafted,
+            attributes: Json(card.attributes.clone()),
+            skill_rating: card.skill_rating,
+        }
+    }
+
+    fn igns<'a>(igns: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+        igns.map(String::as_str).collect()
+    }
+
+    #[test]
+    fn sorts_signups_into_new_changed_unchanged_and_missing() {
+        let existing = vec![
+            player(&card("Alice#NA1", "Gold 2", 4), false),
+            player(&card("Bob#EUW", "Silver 1", 3), false),
+            player(&card("Carol#111", "Iron 1", 1), false),
+            player(&card("Dan#222", "Iron 2", 1), true),
+        ];
+        let incoming = vec![
+            card("Alice#NA1", "Gold 2", 4),
+            card("Bob#EUW", "Gold 1", 4),
+            card("Eve#333", "Platinum 1", 5),
+        ];
+
+        let diff = diff_signups(7, &existing, &incoming);
+
+        assert_eq!(diff.preview_id, 7);
+        assert_eq!(igns(diff.new_players.iter().map(|p| &p.ign)), ["Eve#333"]);
+        assert_eq!(igns(diff.changed.iter().map(|c| &c.ign)), ["Bob#EUW"]);
+        assert_eq!(diff.unchanged, 1);
+        // Drafted players are listed too; committing only removes undrafted ones
+        assert_eq!(igns(diff.missing.iter().map(|p| &p.ign)), ["Carol#111", "Dan#222"]);
+        assert!(diff.problems.is_empty());
+    }
+
+    #[test]
+    fn lists_each_changed_field() {
+        let mut before = card("Bob#EUW", "Silver 1", 3);
+        before.attributes.insert("Timezone".to_string(), "CET".to_string());
+        let mut after = card("Bob#EUW", "Gold 1", 4);
+        after.roles = "Controller, Sentinel".to_string();
+        after.attributes.insert("Timezone".to_string(), "EST".to_string());
+
+        let diff = diff_signups(1, &[player(&before, false)], &[after]);
+
+        let changes: Vec<(&str, &str, &str)> = diff.changed[0]
+            .changes
+            .iter()
+            .map(|c| (c.field.as_str(), c.old.as_str(), c.new.as_str()))
+            .collect();
+        assert_eq!(changes, [
+            ("current_rank", "Silver 1", "Gold 1"),
+            ("roles", "Duelist", "Controller, Sentinel"),
+            ("current_rank_order", "3", "4"),
+            ("skill_rating", "3", "4"),
+            ("attributes", r#"{"Timezone":"CET"}"#, r#"{"Timezone":"EST"}"#),
+        ]);
+    }
+
+    #[test]
+    fn empty_source_marks_everyone_missing() {
+        let existing = vec![player(&card("Alice#NA1", "Gold 2", 4), false)];
+
+        let diff = diff_signups(1, &existing, &[]);
+
+        assert!(diff.new_players.is_empty() && diff.changed.is_empty());
+        assert_eq!(diff.unchanged, 0);
+        assert_eq!(igns(diff.missing.iter().map(|p| &p.ign)), ["Alice#NA1"]);
+    }
+}
@@ -0,0 +1,223 @@
+use reqwest::Client;
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use std::{collections::HashMap, env};
+use yup_oauth2::{read_service_account_key, ServiceAccountAuthenticator};
+
+use crate::dto::tournament_dto::{ColumnMapping, PlayerSourceConfig};
+
+/**
+ * Somewhere signups can be read from. Every source produces the same table as
+ * the Sheets API: a header row followed by one row per signup, each row a JSON
+ * array of cells, ready for `draft_player_formatter::format_responses`.
+ */
+pub trait PlayerSource {
+    fn fetch_rows(&self) -> impl Future<Output = Result<Vec<Value>, String>> + Send;
+}
+
+pub struct GoogleSheetsSource {
+    pub spreadsheet_id: String,
+    pub range: String,
+}
+
+/**
+ * The service account key file, from `GOOGLE_CREDENTIALS_FILE` (default
+ * `credentials.json`). It is server configuration, never part of the
+ * tournament settings, so the API can't point it at other files.
+ */
+fn credentials_file() -> String {
+    env::var("GOOGLE_CREDENTIALS_FILE").unwrap_or_else(|_| "credentials.json".to_string())
+}
+
+impl PlayerSource for GoogleSheetsSource {
+    async fn fetch_rows(&self) -> Result<Vec<Value>, String> {
+        let service_account_key = read_service_account_key(credentials_file())
+            .await
+            .map_err(|_| "Could not read Google credentials".to_string())?;
+
+        let auth = ServiceAccountAuthenticator::builder(service_account_key)
+            .build()
+            .await
+            .map_err(|_| "Failed to create Google auth".to_string())?;
+
+        let token = auth.token(&["https://www.googleapis.com/auth/spreadsheets.readonly"])
+            .await
+            .map_err(|_| "Google API token error".to_string())?;
+
+        let url = format!(
+            "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}",
+            self.spreadsheet_id, self.range
+        );
+
+        let response: Value = Client::new()
+            .get(&url)
+            .bearer_auth(token.token().ok_or("Missing token string")?)
+            .send()
+            .await
+            .map_err(|_| "Failed to send request".to_string())?
+            .json()
+            .await
+            .map_err(|_| "Failed to parse JSON response".to_string())?;
+
+        response.get("values")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .ok_or("No values array found in response".to_string())
+    }
+}
+
+pub struct CsvSource {
+    pub data: String,
+}
+
+impl PlayerSource for CsvSource {
+    async fn fetch_rows(&self) -> Result<Vec<Value>, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(self.data.as_bytes());
+
+        reader
+            .records()
+            .map(|record| {
+                record
+                    .map(|record| Value::Array(record.iter().map(|cell| Value::String(cell.to_string())).collect()))
+                    .map_err(|e| format!("Invalid CSV: {}", e))
+            })
+            .collect()
+    }
+}
+
+pub struct JsonSource {
+    pub data: String,
+}
+
+impl PlayerSource for JsonSource {
+    async fn fetch_rows(&self) -> Result<Vec<Value>, String> {
+        let value: Value = serde_json::from_str(&self.data).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let items = value.as_array().ok_or("Expected a JSON array of signups")?;
+
+        if !items.iter().all(Value::is_object) {
+            return Ok(items.clone());
+        }
+
+        // Objects become rows under a header of every key, in the order the file first lists them
+        // (serde_json's `preserve_order` keeps object keys as written)
+        let mut headers: Vec<String> = vec![];
+        for key in items.iter().filter_map(Value::as_object).flat_map(Map::keys) {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+
+        let mut rows = vec![Value::Array(headers.iter().cloned().map(Value::String).collect())];
+        for item in items.iter().filter_map(Value::as_object) {
+            rows.push(Value::Array(
+                headers.iter().map(|key| item.get(key).cloned().unwrap_or(Value::Null)).collect(),
+            ));
+        }
+
+        Ok(rows)
+    }
+}
+
+/// The configured source and the last uploaded file, if any.
+pub async fn load_source_config(pool: &SqlitePool) -> Result<(PlayerSourceConfig, Option<String>), String> {
+    let (config, upload) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT player_source, source_upload FROM tournament WHERE id = 1"
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Could not load the tournament settings: {}", e))?;
+
+    let config = serde_json::from_str(&config).map_err(|e| format!("Invalid player source setting: {}", e))?;
+    Ok((config, upload))
+}
+
+/// Column mappings saved for each kind of player source.
+pub async fn load_column_mappings(pool: &SqlitePool) -> Result<HashMap<String, ColumnMapping>, String> {
+    let mappings = sqlx::query_scalar::<_, String>("SELECT column_mappings FROM tournament WHERE id = 1")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Could not load the tournament settings: {}", e))?;
+
+    serde_json::from_str(&mappings).map_err(|e| format!("Invalid column mapping setting: {}", e))
+}
+
+/// The column mapping for a kind of player source, or the original form's if none was saved.
+pub async fn load_column_mapping(pool: &SqlitePool, kind: &str) -> Result<ColumnMapping, String> {
+    Ok(load_column_mappings(pool).await?.remove(kind).unwrap_or_default())
+}
+
+/**
+ * Reads the signup table from the tournament's configured source, along with
+ * the column mapping to read it with.
+ */
+pub async fn fetch_signup_rows(pool: &SqlitePool) -> Result<(Vec<Value>, ColumnMapping), String> {
+    let (config, upload) = load_source_config(pool).await?;
+    let mapping = load_column_mapping(pool, config.kind()).await?;
+    let uploaded = || upload.clone().ok_or("No file has been uploaded for this player source".to_string());
+
+    let rows = match config {
+        PlayerSourceConfig::GoogleSheets { spreadsheet_id, range } => {
+            GoogleSheetsSource { spreadsheet_id, range }.fetch_rows().await
+        }
+        PlayerSourceConfig::Csv => CsvSource { data: uploaded()? }.fetch_rows().await,
+        PlayerSourceConfig::Json => JsonSource { data: uploaded()? }.fetch_rows().await,
+    }?;
+
+    Ok((rows, mapping))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn csv_rows_keep_quoted_cells_and_ragged_rows() {
+        let data = "Your Name,In game name (including #),Roles\n\
+                    Alice,Alice#NA1,\"Duelist, Sentinel\"\n\
+                    Bob,Bob#EUW\n";
+
+        let rows = CsvSource { data: data.to_string() }.fetch_rows().await.unwrap();
+
+        assert_eq!(rows, vec![
+            json!(["Your Name", "In game name (including #)", "Roles"]),
+            json!(["Alice", "Alice#NA1", "Duelist, Sentinel"]),
+            json!(["Bob", "Bob#EUW"]),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn json_objects_become_a_header_in_file_order() {
+        let data = r#"[
+            {"name": "Alice", "ign": "Alice#NA1", "current_rank": "Gold 2"},
+            {"name": "Bob", "ign": "Bob#EUW", "current_rank": "Silver 1", "timezone": "CET"}
+        ]"#;
+
+        let rows = JsonSource { data: data.to_string() }.fetch_rows().await.unwrap();
+
+        assert_eq!(rows, vec![
+            json!(["name", "ign", "current_rank", "timezone"]),
+            json!(["Alice", "Alice#NA1", "Gold 2", null]),
+            json!(["Bob", "Bob#EUW", "Silver 1", "CET"]),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn json_rows_are_passed_through() {
+        let data = r#"[["Name", "IGN"], ["Alice", "Alice#NA1"]]"#;
+
+        let rows = JsonSource { data: data.to_string() }.fetch_rows().await.unwrap();
+
+        assert_eq!(rows, vec![json!(["Name", "IGN"]), json!(["Alice", "Alice#NA1"])]);
+    }
+
+    #[tokio::test]
+    async fn json_that_is_not_an_array_is_refused() {
+        let rows = JsonSource { data: r#"{"name": "Alice"}"#.to_string() }.fetch_rows().await;
+
+        assert_eq!(rows.unwrap_err(), "Expected a JSON array of signups");
+    }
+}
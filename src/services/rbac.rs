@@ -0,0 +1,114 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+use crate::dto::claims_dto::Claims;
+use crate::services::auth_user::AuthUser;
+
+/// A user's role, stored in `users.role` and carried in the JWT claims.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Organizer,
+    Captain,
+    Player,
+    #[default]
+    Spectator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Start, stop, pause, resume and undo the draft.
+    ManageDraft,
+    /// Delete any team, not just your own.
+    ManageTeams,
+    /// Change roles and manage other users' accounts.
+    ManageUsers,
+    /// Import signups into the player pool and configure where they come from.
+    ImportPlayers,
+    /// Delete chat messages and mute users.
+    ModerateChat,
+    /// Read and post on the admin websocket channel.
+    AdminChannel,
+    ViewMetrics,
+    /// Browse the audit log.
+    ViewAuditLog,
+    CreateTeam,
+    DraftPick,
+    Chat,
+}
+
+impl Role {
+    pub fn has(self, permission: Permission) -> bool {
+        use Permission::*;
+
+        match self {
+            Role::Admin => true,
+            Role::Organizer => !matches!(permission, ManageUsers | ImportPlayers | ViewAuditLog),
+            Role::Captain => matches!(permission, CreateTeam | DraftPick | Chat),
+            Role::Player => matches!(permission, Chat),
+            Role::Spectator => false,
+        }
+    }
+}
+
+/// Ties a marker type to the permission `Authorized` checks for.
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! permission_markers {
+    ($($name:ident),* $(,)?) => {
+        pub mod require {
+            $(
+                pub struct $name;
+
+                impl super::RequiredPermission for $name {
+                    const PERMISSION: super::Permission = super::Permission::$name;
+                }
+            )*
+        }
+    };
+}
+
+permission_markers!(
+    ManageDraft,
+    ManageUsers,
+    ImportPlayers,
+    ViewMetrics,
+    ViewAuditLog,
+    CreateTeam,
+    DraftPick,
+);
+
+/**
+ * Extractor that authenticates like `AuthUser` and then rejects the request
+ * unless the caller's role grants `P`, e.g. `Authorized<require::ManageDraft>`.
+ */
+pub struct Authorized<P>(pub Claims, pub PhantomData<P>);
+
+impl<S, P> FromRequestParts<S> for Authorized<P>
+where
+    S: Send + Sync,
+    P: RequiredPermission,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let AuthUser(claims) = AuthUser::from_request_parts(parts, state).await?;
+
+        if !claims.role.has(P::PERMISSION) {
+            return Err((StatusCode::FORBIDDEN, "You do not have permission to do that."));
+        }
+
+        Ok(Authorized(claims, PhantomData))
+    }
+}
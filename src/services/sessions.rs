@@ -0,0 +1,269 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use rand::{rng, Rng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tracing::{error, warn};
+
+use crate::dto::{claims_dto::Claims, user_dto::User};
+use crate::services::channels::ChannelRegistry;
+use crate::services::jwt_keys::JwtKeys;
+
+/// Access tokens are short-lived; clients renew them with a refresh token.
+pub fn access_token_ttl() -> Duration {
+    Duration::minutes(15)
+}
+
+pub fn refresh_token_ttl() -> Duration {
+    Duration::days(30)
+}
+
+#[derive(Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+/// 32 random bytes, hex encoded. Used for refresh tokens and JWT ids.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rng().fill(&mut bytes);
+    hex(&bytes)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Refresh tokens are stored hashed so a leaked database cannot be replayed.
+pub fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn database_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Session database error: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue.".to_string())
+}
+
+/**
+ * Issues a new access token and refresh token for a user who just logged in.
+ * Disabled accounts are refused here, which covers every way of signing in.
+ */
+pub async fn issue_session(
+    pool: &SqlitePool,
+    keys: &JwtKeys,
+    user: &User,
+) -> Result<TokenPair, (StatusCode, String)> {
+    if user.disabled {
+        return Err((StatusCode::FORBIDDEN, "This account has been disabled.".to_string()));
+    }
+
+    let token = keys
+        .issue(&user.username, user.role, access_token_ttl())
+        .map_err(|e| {
+            error!("Token encoding failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not issue a token.".to_string())
+        })?;
+
+    let refresh_token = random_token();
+    let token_hash = hash_token(&refresh_token);
+    let now = Utc::now();
+    let expires_at = (now + refresh_token_ttl()).timestamp();
+    let created_at = now.timestamp();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (user_id, token_hash, expires_at, revoked, created_at)
+        VALUES (?, ?, ?, 0, ?)
+        "#,
+        user.id,
+        token_hash,
+        expires_at,
+        created_at
+    )
+    .execute(pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok(TokenPair {
+        token,
+        refresh_token,
+        expires_in: access_token_ttl().num_seconds(),
+    })
+}
+
+/**
+ * Exchanges a refresh token for a new pair. The old refresh token is revoked;
+ * presenting a revoked one again means it was stolen or replayed, so every
+ * session of that user is ended.
+ */
+pub async fn rotate_refresh_token(
+    pool: &SqlitePool,
+    channels: &ChannelRegistry,
+    keys: &JwtKeys,
+    refresh_token: &str,
+) -> Result<TokenPair, (StatusCode, String)> {
+    let token_hash = hash_token(refresh_token);
+    let invalid = (StatusCode::UNAUTHORIZED, "Invalid refresh token.".to_string());
+
+    let stored = sqlx::query_as::<_, (i64, i64, i64, bool)>(
+        "SELECT id, user_id, expires_at, revoked FROM refresh_tokens WHERE token_hash = ?"
+    )
+    .bind(&token_hash)
+    .fetch_optional(pool)
+    .await
+    .map_err(database_error)?;
+
+    let Some((id, user_id, expires_at, revoked)) = stored else {
+        return Err(invalid);
+    };
+
+    if revoked {
+        return Err(refresh_token_reused(pool, channels, user_id).await);
+    }
+
+    if expires_at <= Utc::now().timestamp() {
+        return Err(invalid);
+    }
+
+    /* Only one of two concurrent refreshes with the same token can flip it */
+    let claimed = sqlx::query!("UPDATE refresh_tokens SET revoked = 1 WHERE id = ? AND revoked = 0", id)
+        .execute(pool)
+        .await
+        .map_err(database_error)?
+        .rows_affected();
+
+    if claimed == 0 {
+        return Err(refresh_token_reused(pool, channels, user_id).await);
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(database_error)?
+        .ok_or(invalid)?;
+
+    issue_session(pool, keys, &user).await
+}
+
+async fn refresh_token_reused(pool: &SqlitePool, channels: &ChannelRegistry, user_id: i64) -> (StatusCode, String) {
+    warn!("Revoked refresh token reused for user {}; ending all sessions.", user_id);
+    match invalidate_all_sessions(pool, channels, user_id).await {
+        Ok(()) => (StatusCode::UNAUTHORIZED, "Invalid refresh token.".to_string()),
+        Err(e) => e,
+    }
+}
+
+/**
+ * Revokes one access token until it would have expired anyway.
+ */
+pub async fn revoke_access_token(pool: &SqlitePool, claims: &Claims) -> Result<(), (StatusCode, String)> {
+    let now = Utc::now().timestamp();
+    let expires_at = claims.exp as i64;
+
+    sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at <= ?", now)
+        .execute(pool)
+        .await
+        .map_err(database_error)?;
+
+    sqlx::query!(
+        "INSERT OR IGNORE INTO revoked_tokens (jti, expires_at) VALUES (?, ?)",
+        claims.jti,
+        expires_at
+    )
+    .execute(pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok(())
+}
+
+pub async fn revoke_refresh_token(
+    pool: &SqlitePool,
+    username: &str,
+    refresh_token: &str,
+) -> Result<(), (StatusCode, String)> {
+    let token_hash = hash_token(refresh_token);
+
+    sqlx::query!(
+        r#"
+        UPDATE refresh_tokens SET revoked = 1
+        WHERE token_hash = ? AND user_id = (SELECT id FROM users WHERE username = ?)
+        "#,
+        token_hash,
+        username
+    )
+    .execute(pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok(())
+}
+
+/**
+ * Ends every session of a user: access tokens issued until now stop working,
+ * all refresh tokens are revoked and their open websockets are closed.
+ */
+pub async fn invalidate_all_sessions(
+    pool: &SqlitePool,
+    channels: &ChannelRegistry,
+    user_id: i64,
+) -> Result<(), (StatusCode, String)> {
+    let now = Utc::now().timestamp_millis();
+
+    let username = sqlx::query_scalar::<_, String>(
+        "UPDATE users SET sessions_valid_after = ? WHERE id = ? RETURNING username"
+    )
+    .bind(now)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(database_error)?;
+
+    sqlx::query!("UPDATE refresh_tokens SET revoked = 1 WHERE user_id = ?", user_id)
+        .execute(pool)
+        .await
+        .map_err(database_error)?;
+
+    if let Some(username) = username {
+        channels.disconnect_user(&username);
+    }
+
+    Ok(())
+}
+
+/**
+ * Rejects tokens that were revoked by logout, belong to a deleted user, or
+ * were issued before the user's sessions were invalidated.
+ */
+pub async fn ensure_session_active(pool: &SqlitePool, claims: &Claims) -> Result<(), (StatusCode, &'static str)> {
+    let revoked = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM revoked_tokens WHERE jti = ?")
+        .bind(&claims.jti)
+        .fetch_one(pool)
+        .await;
+
+    let valid_after = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT sessions_valid_after FROM users WHERE username = ?"
+    )
+    .bind(&claims.sub)
+    .fetch_optional(pool)
+    .await;
+
+    match (revoked, valid_after) {
+        (Ok(0), Ok(Some(valid_after))) => {
+            if valid_after.is_some_and(|t| claims.issued_at_ms() < t) {
+                Err((StatusCode::UNAUTHORIZED, "Session has been ended"))
+            } else {
+                Ok(())
+            }
+        }
+        (Ok(_), Ok(_)) => Err((StatusCode::UNAUTHORIZED, "Session has been ended")),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to check session: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "There was a database issue."))
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// One problem with one field of a request body.
+#[derive(Debug, Serialize)]
+pub struct FieldProblem {
+    pub field: String,
+    pub problem: String,
+}
+
+/**
+ * Rejection for request bodies that fail validation: 422 with every problem,
+ * e.g. `{"errors": [{"field": "username", "problem": "must be 3 to 32 characters"}]}`.
+ */
+#[derive(Debug)]
+pub struct Invalid(pub Vec<FieldProblem>);
+
+impl Invalid {
+    pub fn field(field: &str, problem: &str) -> Self {
+        Self(vec![FieldProblem { field: field.to_string(), problem: problem.to_string() }])
+    }
+}
+
+impl IntoResponse for Invalid {
+    fn into_response(self) -> Response {
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "errors": self.0 }))).into_response()
+    }
+}
+
+impl From<ValidationErrors> for Invalid {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut problems: Vec<FieldProblem> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |error| FieldProblem {
+                    field: field.to_string(),
+                    problem: error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| error.code.to_string()),
+                })
+            })
+            .collect();
+
+        problems.sort_by(|a, b| a.field.cmp(&b.field));
+        Self(problems)
+    }
+}
+
+/**
+ * Extractor that deserializes a JSON body like `Json<T>` and then runs the
+ * `#[validate(...)]` rules declared on `T`, rejecting with `Invalid`.
+ */
+pub struct Valid<T>(pub T);
+
+impl<S, T> FromRequest<S> for Valid<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection: JsonRejection| {
+                let status = rejection.status();
+                let problem = FieldProblem { field: "body".to_string(), problem: rejection.body_text() };
+                (status, Json(serde_json::json!({ "errors": [problem] }))).into_response()
+            })?;
+
+        value.validate().map_err(|e| Invalid::from(e).into_response())?;
+
+        Ok(Valid(value))
+    }
+}
+
+/// Usernames are used in URLs and channel names, so they are kept to a safe set of characters.
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("username_characters")
+            .with_message("may only contain letters, digits, '_', '-' and '.'".into()))
+    }
+}
+
+/**
+ * An in-game name is `Name#Tag` with a non-empty name and a tag of letters and
+ * digits. Tag lengths differ between games (Riot IDs use 3 to 5, BattleTags
+ * 4 or 5 digits), so any tag up to 16 characters is accepted.
+ */
+pub fn validate_ign(ign: &str) -> Result<(), ValidationError> {
+    let valid = match ign.rsplit_once('#') {
+        Some((name, tag)) => {
+            !name.trim().is_empty()
+                && (1..=16).contains(&tag.chars().count())
+                && tag.chars().all(|c| c.is_alphanumeric())
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("ign_format")
+            .with_message("must look like Name#Tag with a tag of letters and digits".into()))
+    }
+}
+
+/// Rejects values that are only whitespace, which `length(min = 1)` lets through.
+pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(ValidationError::new("blank").with_message("must not be blank".into()))
+    } else {
+        Ok(())
+    }
+}
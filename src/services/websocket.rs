@@ -1,15 +1,32 @@
 
 use axum::{
-    extract::{Extension, ws::{WebSocket, WebSocketUpgrade, Message}},
+    body::Bytes,
+    extract::{ConnectInfo, Extension, Query, ws::{WebSocket, WebSocketUpgrade, Message}},
+    http::StatusCode,
     response::IntoResponse,
+    Json,
 };
+use serde::Deserialize;
+use serde_json::Value;
 use sqlx::{SqlitePool};
-use tokio::sync::broadcast;
-use tracing::{info, error};
-use crate::dto::{draft_dto::{SharedDraftState, UpdateDraft}, team_dto::{Team, TeamsUpdate}, player_dto::{Player, PlayerUpdate}};
+use std::net::{IpAddr, SocketAddr};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{self, Instant};
+use tracing::{info, error, warn};
+use crate::dto::{claims_dto::Claims, draft_dto::{SharedDraftState, UpdateDraft}, team_dto::{Team, TeamsUpdate}, player_dto::{Player, PlayerSnapshot, PlayerUpdate}, ws_dto::{ChannelMessage, ClientCommand, ClientMessage, CommandReply, EventKind, OnTheClock, PlayerSnapshotRequest, ServerEvent}};
+use crate::routes::draft::{draft_pick_internal, set_paused_internal, undo_pick_internal};
+use crate::routes::players::search_players;
+use crate::services::audit::{draft_summary, record, record_draft_action, AuditEntry};
+use crate::services::auth_user::decode_token;
+use crate::services::sessions::ensure_session_active;
+use crate::services::rbac::{require, Authorized, Permission};
+use crate::services::channels::{team_members, Channel, SharedChannelRegistry};
+use crate::services::jwt_keys::SharedJwtKeys;
+use crate::services::chat::{delete_chat_message, ensure_not_muted, mute_user, post_chat_message, unmute_user, SharedChatLimiter};
+use crate::services::ws_connections::{ConnectionGuard, SharedConnectionTracker};
 use futures_util::{StreamExt, SinkExt};
 
-pub async fn send_team_update(pool: &SqlitePool, tx: &broadcast::Sender<String>) {
+pub async fn send_team_update(pool: &SqlitePool, tx: &broadcast::Sender<ServerEvent>) {
     let teams = sqlx::query_as::<_, Team>("SELECT * FROM teams")
         .fetch_all(pool)
         .await
@@ -27,10 +44,10 @@ pub async fn send_team_update(pool: &SqlitePool, tx: &broadcast::Sender<String>)
             return;
         }
     };
-    let _ = tx.send(update_msg);
+    let _ = tx.send(ServerEvent { kind: EventKind::TeamsUpdate, data: update_msg });
 }
 
-pub async fn send_draft_update(tx: &broadcast::Sender<String>, state: &SharedDraftState) {
+pub async fn send_draft_update(tx: &broadcast::Sender<ServerEvent>, state: &SharedDraftState) {
     let state_guard = state.read().await;
 
     let update_msg = UpdateDraft {
@@ -40,7 +57,7 @@ pub async fn send_draft_update(tx: &broadcast::Sender<String>, state: &SharedDra
 
     match serde_json::to_string(&update_msg) {
         Ok(json) => {
-            let _ = tx.send(json);
+            let _ = tx.send(ServerEvent { kind: EventKind::DraftUpdate, data: json });
         }
         Err(e) => {
             tracing::error!("Failed to serialize draft update message: {}", e);
@@ -48,7 +65,7 @@ pub async fn send_draft_update(tx: &broadcast::Sender<String>, state: &SharedDra
     }
 }
 
-pub async fn send_player_update(pool: &SqlitePool, tx: &broadcast::Sender<String>) {
+pub async fn send_player_update(pool: &SqlitePool, tx: &broadcast::Sender<ServerEvent>) {
     let player_result = sqlx::query_as::<_, Player>("SELECT * FROM players")
         .fetch_all(pool)
         .await
@@ -61,7 +78,7 @@ pub async fn send_player_update(pool: &SqlitePool, tx: &broadcast::Sender<String
 
     match serde_json::to_string(&update_msg) {
         Ok(json) => {
-            let _ = tx.send(json);
+            let _ = tx.send(ServerEvent { kind: EventKind::PlayerUpdate, data: json });
         }
         Err(e) => {
             tracing::error!("Failed to serialize player update message: {}", e);
@@ -70,31 +87,570 @@ pub async fn send_player_update(pool: &SqlitePool, tx: &broadcast::Sender<String
 }
 
 /* Web Socket stuff */
+#[derive(Deserialize)]
+pub struct WebSocketParams {
+    pub token: Option<String>,
+}
+
+/// Shared handles a websocket connection needs to run commands and deliver messages.
+#[derive(Clone)]
+struct SocketContext {
+    tx: broadcast::Sender<ServerEvent>,
+    state: SharedDraftState,
+    pool: SqlitePool,
+    connections: SharedConnectionTracker,
+    channels: SharedChannelRegistry,
+    chat: SharedChatLimiter,
+    ip: IpAddr,
+}
+
+/**
+ * Browsers cannot set headers on a websocket upgrade, so clients that want to
+ * send commands pass their JWT as `/ws?token=...`. Spectators connect without one.
+ */
+#[allow(clippy::too_many_arguments)]
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
-    Extension(tx): Extension<broadcast::Sender<String>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, tx))
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<WebSocketParams>,
+    Extension(tx): Extension<broadcast::Sender<ServerEvent>>,
+    Extension(state): Extension<SharedDraftState>,
+    Extension(pool): Extension<SqlitePool>,
+    Extension(connections): Extension<SharedConnectionTracker>,
+    Extension(channels): Extension<SharedChannelRegistry>,
+    Extension(chat): Extension<SharedChatLimiter>,
+    Extension(keys): Extension<SharedJwtKeys>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let claims = match params.token {
+        Some(token) => {
+            let claims = decode_token(&keys, &token)?;
+            ensure_session_active(&pool, &claims).await?;
+            Some(claims)
+        }
+        None => None,
+    };
+
+    let guard = connections
+        .try_register(addr.ip(), claims.as_ref().map(|c| c.sub.as_str()))
+        .map_err(|reason| {
+            warn!("Refused websocket from {}: {}", addr, reason);
+            (StatusCode::TOO_MANY_REQUESTS, reason)
+        })?;
+
+    let ctx = SocketContext { tx, state, pool, connections, channels, chat, ip: addr.ip() };
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, ctx, guard, claims)))
 }
 
-async fn handle_socket(socket: WebSocket, tx: broadcast::Sender<String>) {
+async fn handle_socket(
+    socket: WebSocket,
+    ctx: SocketContext,
+    _guard: ConnectionGuard,
+    claims: Option<Claims>,
+) {
     let (mut sender, mut receiver) = socket.split();
-    let mut rx = tx.subscribe();
+    let mut rx = ctx.tx.subscribe();
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<Message>();
+
+    // Authenticated connections also receive their user, team and admin channels
+    let _subscription = claims.as_ref().map(|c| ctx.channels.subscribe(c, reply_tx.clone()));
+
+    // Task to send broadcasts, direct messages, command replies and pings to this client
+    let mut send_task = tokio::spawn(async move {
+        loop {
+            let msg = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Ok(event) => Message::Text(event.data.into()),
+                    Err(_) => break,
+                },
+                Some(msg) = reply_rx.recv() => msg,
+            };
 
-    // Task to send messages to this client
-    let send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if sender.send(Message::Text(msg.into())).await.is_err() {
+            // The registry closes a connection whose session was ended
+            let closing = matches!(msg, Message::Close(_));
+            if sender.send(msg).await.is_err() || closing {
                 break;
             }
         }
     });
 
-    // Receive messages from this client and broadcast them
-    while let Some(Ok(Message::Text(msg))) = receiver.next().await {
-        let _ = tx.send(msg.to_string());
+    let config = &ctx.connections.config;
+    let mut heartbeat = time::interval(config.ping_interval);
+    let mut last_seen = Instant::now();
+
+    // Run commands from this client. Nothing a client sends is broadcast as it is.
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                let msg = match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(msg)) => msg,
+                };
+                last_seen = Instant::now();
+
+                let Message::Text(msg) = msg else { continue };
+
+                let reply = handle_text(&ctx, claims.as_ref(), &msg).await;
+
+                if let Some(reply) = reply {
+                    let _ = reply_tx.send(Message::Text(reply.into()));
+                }
+            }
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > config.pong_timeout {
+                    info!("Closing websocket that has been silent for {:?}.", last_seen.elapsed());
+                    ctx.connections.record_timeout();
+                    break;
+                }
+                let _ = reply_tx.send(Message::Ping(Bytes::new()));
+            }
+            _ = &mut send_task => break,
+        }
     }
 
     // Clean up
     send_task.abort();
-}
\ No newline at end of file
+}
+
+pub async fn get_ws_metrics(
+    Extension(connections): Extension<SharedConnectionTracker>,
+    _: Authorized<require::ViewMetrics>,
+) -> impl IntoResponse {
+    (StatusCode::OK, Json(connections.metrics()))
+}
+
+fn message_type(msg: &str) -> Option<String> {
+    serde_json::from_str::<Value>(msg)
+        .ok()?
+        .get("type")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/**
+ * Answers one text frame from a client: a command or a `players` request.
+ * Anything else is refused, so clients can't pass off their own frames as
+ * draft, chat or channel events.
+ */
+async fn handle_text(ctx: &SocketContext, claims: Option<&Claims>, msg: &str) -> Option<String> {
+    match message_type(msg) {
+        Some(t) if t == "players" => player_snapshot(&ctx.pool, msg).await,
+        Some(t) if ClientCommand::TYPES.contains(&t.as_str()) => match serde_json::from_str::<ClientMessage>(msg) {
+            Ok(message) => {
+                let result = run_command(message.command, claims, ctx).await;
+                command_reply(message.id, result)
+            }
+            Err(e) => command_reply(None, Err((StatusCode::BAD_REQUEST, format!("Invalid command: {}", e)))),
+        },
+        _ => command_reply(None, Err((StatusCode::BAD_REQUEST, "Unknown message.".to_string()))),
+    }
+}
+
+/// Answers a `players` request with the filtered player list.
+async fn player_snapshot(pool: &SqlitePool, msg: &str) -> Option<String> {
+    let request = match serde_json::from_str::<PlayerSnapshotRequest>(msg) {
+        Ok(request) => request,
+        Err(e) => return command_reply(None, Err((StatusCode::BAD_REQUEST, format!("Invalid players request: {}", e)))),
+    };
+
+    let (players, total) = match search_players(pool, &request.query).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to search players: {:?}", e);
+            return command_reply(request.id, Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch players".to_string())));
+        }
+    };
+
+    let snapshot = PlayerSnapshot {
+        r#type: "player_snapshot".to_string(),
+        id: request.id,
+        total,
+        players
+    };
+
+    match serde_json::to_string(&snapshot) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            error!("Failed to serialize player snapshot: {}", e);
+            None
+        }
+    }
+}
+
+async fn run_command(
+    command: ClientCommand,
+    claims: Option<&Claims>,
+    ctx: &SocketContext,
+) -> Result<String, (StatusCode, String)> {
+    let claims = claims.ok_or((StatusCode::UNAUTHORIZED, "You must be logged in to send commands.".to_string()))?;
+
+    // The socket outlives the token it was opened with, so a demoted or logged out user is caught here
+    ensure_session_active(&ctx.pool, claims)
+        .await
+        .map_err(|(status, message)| (status, message.to_string()))?;
+    let SocketContext { tx, state, pool, channels, chat, ip, .. } = ctx;
+
+    if !claims.role.has(command.required_permission()) {
+        return Err((StatusCode::FORBIDDEN, "You do not have permission to do that.".to_string()));
+    }
+
+    let audit = audit_details(&command);
+    let before = match audit {
+        Some((_, _, true)) => Some(draft_summary(&*state.read().await)),
+        _ => None,
+    };
+
+    let result = match command {
+        ClientCommand::Pick { ign } => draft_pick_internal(state, tx, pool, channels, &claims.sub, &ign).await,
+        ClientCommand::Pause => set_paused_internal(state, tx, pool, channels, true).await,
+        ClientCommand::Resume => set_paused_internal(state, tx, pool, channels, false).await,
+        ClientCommand::Undo => undo_pick_internal(state, tx, pool, channels).await,
+        ClientCommand::Send { channel, message } => send_channel_message(pool, channels, claims, &channel, message).await,
+        ClientCommand::Chat { message } => post_chat_message(pool, tx, chat, &claims.sub, &message).await,
+        ClientCommand::ChatDelete { message_id } => delete_chat_message(pool, tx, message_id).await,
+        ClientCommand::ChatMute { username, minutes } => mute_user(pool, &username, minutes, &claims.sub).await,
+        ClientCommand::ChatUnmute { username } => unmute_user(pool, &username).await,
+    };
+
+    if let (Ok(message), Some((action, target, is_draft))) = (&result, audit) {
+        let entry = AuditEntry {
+            actor: &claims.sub,
+            action,
+            target: target.as_deref(),
+            ip: Some(*ip),
+            ..Default::default()
+        };
+
+        if is_draft {
+            record_draft_action(pool, state, AuditEntry { before, ..entry }).await;
+        } else {
+            record(pool, AuditEntry { after: Some(message.clone()), ..entry }).await;
+        }
+    }
+
+    result
+}
+
+/**
+ * The audit log action and target for commands that change the draft or
+ * moderate other users, and whether the draft should be summarised with it.
+ */
+fn audit_details(command: &ClientCommand) -> Option<(&'static str, Option<String>, bool)> {
+    match command {
+        ClientCommand::Pick { ign } => Some(("draft_pick", Some(ign.clone()), true)),
+        ClientCommand::Pause => Some(("pause_draft", None, true)),
+        ClientCommand::Resume => Some(("resume_draft", None, true)),
+        ClientCommand::Undo => Some(("undo_pick", None, true)),
+        ClientCommand::ChatDelete { message_id } => Some(("chat_delete", Some(message_id.to_string()), false)),
+        ClientCommand::ChatMute { username, .. } => Some(("chat_mute", Some(username.clone()), false)),
+        ClientCommand::ChatUnmute { username } => Some(("chat_unmute", Some(username.clone()), false)),
+        ClientCommand::Send { .. } | ClientCommand::Chat { .. } => None,
+    }
+}
+
+/**
+ * Delivers a message on a private channel. Team channels are limited to the
+ * team's members and the admin channel to staff; anyone allowed to chat may
+ * message another user directly.
+ */
+async fn send_channel_message(
+    pool: &SqlitePool,
+    channels: &SharedChannelRegistry,
+    claims: &Claims,
+    channel_name: &str,
+    message: String,
+) -> Result<String, (StatusCode, String)> {
+    let channel = Channel::parse(channel_name)
+        .ok_or((StatusCode::BAD_REQUEST, format!("Unknown channel '{}'.", channel_name)))?;
+    let is_staff = claims.role.has(Permission::AdminChannel);
+
+    ensure_not_muted(pool, &claims.sub).await?;
+
+    let recipients = match &channel {
+        Channel::User(username) => vec![username.clone()],
+        Channel::Team(team_id) => {
+            let members = team_members(pool, *team_id).await.map_err(|e| {
+                error!("Failed to load team members: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load team members".to_string())
+            })?;
+
+            if !is_staff && !members.contains(&claims.sub) {
+                return Err((StatusCode::FORBIDDEN, "You are not a member of this team.".to_string()));
+            }
+            members
+        }
+        Channel::Admin => {
+            if !is_staff {
+                return Err((StatusCode::FORBIDDEN, "You do not have access to the admin channel.".to_string()));
+            }
+            vec![]
+        }
+    };
+
+    let msg = ChannelMessage {
+        r#type: "channel_message".to_string(),
+        channel: channel.name(),
+        from: claims.sub.clone(),
+        message,
+    };
+
+    let json = serde_json::to_string(&msg)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize message: {}", e)))?;
+    let delivered = match channel {
+        Channel::Admin => channels.send_to_permitted(Permission::AdminChannel, &json),
+        _ => channels.send_to_users(&recipients, &json),
+    };
+
+    Ok(format!("Delivered to {} connection(s).", delivered))
+}
+
+/**
+ * Tells the captain of the team on the clock that it is their turn, on their
+ * `user:` channel only.
+ */
+pub async fn notify_on_the_clock(channels: &SharedChannelRegistry, state: &SharedDraftState) {
+    let state_guard = state.read().await;
+
+    if state_guard.phase != "Drafting" {
+        return;
+    }
+
+    let Some(team) = state_guard.teams.0.get(state_guard.current_turn as usize) else { return };
+    let Some(captain) = &team.created_by else { return };
+
+    let msg = OnTheClock {
+        r#type: "on_the_clock".to_string(),
+        channel: Channel::User(captain.clone()).name(),
+        team_id: team.id,
+        team_name: team.name.clone(),
+    };
+
+    match serde_json::to_string(&msg) {
+        Ok(json) => {
+            channels.send_to_user(captain, &json);
+        }
+        Err(e) => {
+            error!("Failed to serialize on the clock message: {}", e);
+        }
+    }
+}
+
+fn command_reply(id: Option<String>, result: Result<String, (StatusCode, String)>) -> Option<String> {
+    let reply = match result {
+        Ok(message) => CommandReply { r#type: "ack".to_string(), id, message },
+        Err((_, message)) => CommandReply { r#type: "error".to_string(), id, message },
+    };
+
+    match serde_json::to_string(&reply) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            error!("Failed to serialize command reply: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::draft_dto::DraftState;
+    use crate::services::channels::ChannelRegistry;
+    use crate::services::chat::ChatLimiter;
+    use crate::services::rbac::Role;
+    use crate::services::sessions::invalidate_all_sessions;
+    use crate::services::ws_connections::{ConnectionTracker, WebSocketConfig};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::types::Json as SqlxJson;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    async fn context() -> SocketContext {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        for username in ["alice", "bob", "carol"] {
+            sqlx::query("INSERT INTO users (name, username, ign, password) VALUES (?, ?, ?, 'x')")
+                .bind(username)
+                .bind(username)
+                .bind(format!("{}#111", username))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let (tx, _) = broadcast::channel(16);
+        let state = DraftState {
+            phase: "Setup".to_string(),
+            teams: SqlxJson(vec![]),
+            current_turn: 0,
+            drafted_players: SqlxJson(vec![]),
+            direction: 1,
+            pick_history: SqlxJson(vec![]),
+        };
+
+        SocketContext {
+            tx,
+            state: Arc::new(RwLock::new(state)),
+            pool,
+            connections: Arc::new(ConnectionTracker::new(WebSocketConfig::from_env())),
+            channels: Arc::new(ChannelRegistry::default()),
+            chat: Arc::new(ChatLimiter::default()),
+            ip: "127.0.0.1".parse().unwrap(),
+        }
+    }
+
+    fn claims(username: &str, role: Role) -> Claims {
+        Claims {
+            sub: username.to_string(),
+            exp: usize::MAX,
+            iat: 0,
+            iat_ms: 0,
+            jti: String::new(),
+            iss: String::new(),
+            aud: String::new(),
+            role,
+        }
+    }
+
+    fn received(rx: &mut mpsc::UnboundedReceiver<Message>) -> Vec<String> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|msg| match msg {
+                Message::Text(text) => Some(text.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn user_channel_message_reaches_only_that_user() {
+        let ctx = context().await;
+        let (alice_tx, mut alice_rx) = mpsc::unbounded_channel();
+        let (bob_tx, mut bob_rx) = mpsc::unbounded_channel();
+        let (carol_tx, mut carol_rx) = mpsc::unbounded_channel();
+        let _alice = ctx.channels.subscribe(&claims("alice", Role::Player), alice_tx);
+        let _bob = ctx.channels.subscribe(&claims("bob", Role::Player), bob_tx);
+        let _carol = ctx.channels.subscribe(&claims("carol", Role::Admin), carol_tx);
+        let mut broadcasts = ctx.tx.subscribe();
+
+        let alice = claims("alice", Role::Player);
+        let reply = handle_text(&ctx, Some(&alice), r#"{"type":"send","channel":"user:bob","message":"hi"}"#).await;
+
+        assert!(reply.unwrap().contains("\"ack\""));
+        let to_bob = received(&mut bob_rx);
+        assert_eq!(to_bob.len(), 1);
+        assert!(to_bob[0].contains("\"channel\":\"user:bob\""));
+        assert!(received(&mut alice_rx).is_empty());
+        assert!(received(&mut carol_rx).is_empty());
+        assert!(broadcasts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn team_channel_message_stays_on_the_team() {
+        let ctx = context().await;
+        sqlx::query(
+            r#"
+            INSERT INTO teams (id, name, team_size, team_money, is_picking, created_by)
+            VALUES (1, 'Red', 5, 0, 0, 'alice'), (2, 'Blue', 5, 0, 0, 'carol')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE users SET team_id = 1 WHERE username = 'bob'")
+            .execute(&ctx.pool)
+            .await
+            .unwrap();
+
+        let (alice_tx, mut alice_rx) = mpsc::unbounded_channel();
+        let (bob_tx, mut bob_rx) = mpsc::unbounded_channel();
+        let (carol_tx, mut carol_rx) = mpsc::unbounded_channel();
+        let _alice = ctx.channels.subscribe(&claims("alice", Role::Captain), alice_tx);
+        let _bob = ctx.channels.subscribe(&claims("bob", Role::Player), bob_tx);
+        let _carol = ctx.channels.subscribe(&claims("carol", Role::Captain), carol_tx);
+
+        let alice = claims("alice", Role::Captain);
+        handle_text(&ctx, Some(&alice), r#"{"type":"send","channel":"team:1","message":"plan"}"#).await;
+
+        assert_eq!(received(&mut alice_rx).len(), 1);
+        assert_eq!(received(&mut bob_rx).len(), 1);
+        assert!(received(&mut carol_rx).is_empty());
+
+        // A captain can't post on another team's channel
+        let reply = handle_text(&ctx, Some(&alice), r#"{"type":"send","channel":"team:2","message":"spy"}"#).await;
+        assert!(reply.unwrap().contains("\"error\""));
+        assert!(received(&mut carol_rx).is_empty());
+    }
+
+    #[tokio::test]
+    async fn forged_channel_frames_are_refused() {
+        let ctx = context().await;
+        let (bob_tx, mut bob_rx) = mpsc::unbounded_channel();
+        let _bob = ctx.channels.subscribe(&claims("bob", Role::Player), bob_tx);
+        let mut broadcasts = ctx.tx.subscribe();
+
+        let forged = [
+            r#"{"type":"channel_message","channel":"user:bob","from":"admin","message":"hi"}"#,
+            r#"{"type":"on_the_clock","channel":"user:bob","team_id":1,"team_name":"Red"}"#,
+            r#"{"type":"chat_message","message":{"id":1,"body":"fake"}}"#,
+            r#"{"type":"draft_update","draft_state":{}}"#,
+        ];
+
+        for frame in forged {
+            let reply = handle_text(&ctx, None, frame).await;
+            assert!(reply.unwrap().contains("Unknown message."));
+        }
+
+        assert!(received(&mut bob_rx).is_empty());
+        assert!(broadcasts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn muted_user_cannot_send_on_a_channel() {
+        let ctx = context().await;
+        let (bob_tx, mut bob_rx) = mpsc::unbounded_channel();
+        let _bob = ctx.channels.subscribe(&claims("bob", Role::Player), bob_tx);
+        let carol = claims("carol", Role::Admin);
+        let alice = claims("alice", Role::Player);
+
+        for minutes in ["0", "-5", "9223372036854775807"] {
+            let mute = format!(r#"{{"type":"chat_mute","username":"alice","minutes":{}}}"#, minutes);
+            let reply = handle_text(&ctx, Some(&carol), &mute).await;
+            assert!(reply.unwrap().contains("\"error\""), "{} minutes was accepted", minutes);
+        }
+
+        let reply = handle_text(&ctx, Some(&carol), r#"{"type":"chat_mute","username":"alice","minutes":10}"#).await;
+        assert!(reply.unwrap().contains("\"ack\""));
+
+        let reply = handle_text(&ctx, Some(&alice), r#"{"type":"send","channel":"user:bob","message":"hi"}"#).await;
+        assert!(reply.unwrap().contains("You are muted until"));
+        assert!(received(&mut bob_rx).is_empty());
+    }
+
+    #[tokio::test]
+    async fn ended_sessions_stop_receiving_channel_messages() {
+        let ctx = context().await;
+        let (carol_tx, mut carol_rx) = mpsc::unbounded_channel();
+        let (alice_tx, mut alice_rx) = mpsc::unbounded_channel();
+        let _carol = ctx.channels.subscribe(&claims("carol", Role::Admin), carol_tx);
+        let mut alice = claims("alice", Role::Admin);
+        alice.jti = "alice-token".to_string();
+        let _alice = ctx.channels.subscribe(&alice, alice_tx);
+
+        let carol_id = sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username = 'carol'")
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+        invalidate_all_sessions(&ctx.pool, &ctx.channels, carol_id).await.unwrap();
+        assert_eq!(ctx.channels.disconnect_token("alice-token"), 1);
+
+        assert!(matches!(carol_rx.try_recv(), Ok(Message::Close(_))));
+        assert!(matches!(alice_rx.try_recv(), Ok(Message::Close(_))));
+        assert_eq!(ctx.channels.send_to_permitted(Permission::ManageUsers, "{}"), 0);
+        assert!(received(&mut carol_rx).is_empty());
+        assert!(received(&mut alice_rx).is_empty());
+    }
+}
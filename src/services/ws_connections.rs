@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    env,
+    net::IpAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use serde::Serialize;
+use tracing::warn;
+
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    pub ping_interval: Duration,
+    pub pong_timeout: Duration,
+    pub max_connections_per_ip: usize,
+    pub max_connections_per_user: usize,
+}
+
+impl WebSocketConfig {
+    /**
+     * Reads `WS_PING_INTERVAL_SECS`, `WS_PONG_TIMEOUT_SECS`, `WS_MAX_CONNECTIONS_PER_IP`
+     * and `WS_MAX_CONNECTIONS_PER_USER`, falling back to the defaults for anything unset.
+     */
+    pub fn from_env() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(env_or("WS_PING_INTERVAL_SECS", 25)),
+            pong_timeout: Duration::from_secs(env_or("WS_PONG_TIMEOUT_SECS", 60)),
+            max_connections_per_ip: env_or("WS_MAX_CONNECTIONS_PER_IP", 20),
+            max_connections_per_user: env_or("WS_MAX_CONNECTIONS_PER_USER", 5),
+        }
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid value for {}: {}", key, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    per_ip: HashMap<IpAddr, usize>,
+    per_user: HashMap<String, usize>,
+    active: usize,
+    opened_total: u64,
+    timed_out_total: u64,
+}
+
+#[derive(Serialize)]
+pub struct ConnectionMetrics {
+    pub active: usize,
+    pub authenticated: usize,
+    pub unique_ips: usize,
+    pub opened_total: u64,
+    pub timed_out_total: u64,
+}
+
+/**
+ * Keeps count of open websockets per IP and per user so `/ws` can enforce
+ * connection limits and report how many sockets are alive.
+ */
+pub struct ConnectionTracker {
+    pub config: WebSocketConfig,
+    counts: Mutex<Counts>,
+}
+
+pub type SharedConnectionTracker = Arc<ConnectionTracker>;
+
+impl ConnectionTracker {
+    pub fn new(config: WebSocketConfig) -> Self {
+        Self {
+            config,
+            counts: Mutex::new(Counts::default()),
+        }
+    }
+
+    /**
+     * Registers a new connection, or returns the reason it was refused. The
+     * connection is released when the returned guard is dropped.
+     */
+    pub fn try_register(
+        self: &Arc<Self>,
+        ip: IpAddr,
+        user: Option<&str>,
+    ) -> Result<ConnectionGuard, &'static str> {
+        let mut counts = self.counts.lock().unwrap();
+
+        if counts.per_ip.get(&ip).copied().unwrap_or(0) >= self.config.max_connections_per_ip {
+            return Err("Too many connections from this address");
+        }
+
+        if let Some(user) = user {
+            if counts.per_user.get(user).copied().unwrap_or(0) >= self.config.max_connections_per_user {
+                return Err("Too many connections for this user");
+            }
+            *counts.per_user.entry(user.to_string()).or_insert(0) += 1;
+        }
+
+        *counts.per_ip.entry(ip).or_insert(0) += 1;
+        counts.active += 1;
+        counts.opened_total += 1;
+
+        Ok(ConnectionGuard {
+            tracker: Arc::clone(self),
+            ip,
+            user: user.map(str::to_string),
+        })
+    }
+
+    pub fn record_timeout(&self) {
+        self.counts.lock().unwrap().timed_out_total += 1;
+    }
+
+    pub fn metrics(&self) -> ConnectionMetrics {
+        let counts = self.counts.lock().unwrap();
+
+        ConnectionMetrics {
+            active: counts.active,
+            authenticated: counts.per_user.values().sum(),
+            unique_ips: counts.per_ip.len(),
+            opened_total: counts.opened_total,
+            timed_out_total: counts.timed_out_total,
+        }
+    }
+}
+
+pub struct ConnectionGuard {
+    tracker: SharedConnectionTracker,
+    ip: IpAddr,
+    user: Option<String>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut counts = self.tracker.counts.lock().unwrap();
+        counts.active -= 1;
+
+        if let Some(count) = counts.per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.per_ip.remove(&self.ip);
+            }
+        }
+
+        if let Some(user) = &self.user
+            && let Some(count) = counts.per_user.get_mut(user)
+        {
+            *count -= 1;
+            if *count == 0 {
+                counts.per_user.remove(user);
+            }
+        }
+    }
+}